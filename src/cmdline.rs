@@ -0,0 +1,279 @@
+/// Long options consumed by the shimloader. Each takes a value, either as the next
+/// argument or inline as `--option=value`, unless it is one of the `SHIM_FLAGS`.
+pub const SHIM_OPTIONS: &[&str] = &[
+    "mod-dir",
+    "pak-dir",
+    "cfg-dir",
+    "map",
+    "hide",
+    "exclude",
+    "generate-mod-list",
+    "ue4ss-setting",
+    "shim-config",
+    "shim-log-level",
+    "shim-on-error",
+];
+
+/// Shimloader options that don't take a value.
+pub const SHIM_FLAGS: &[&str] = &["generate-mod-list"];
+
+/// A single argument of a command line, along with where it sits in the original string.
+#[derive(Debug, PartialEq, Eq)]
+struct Arg {
+    value: String,
+    start: usize,
+    end: usize,
+}
+
+fn is_blank(c: char) -> bool {
+    c == ' ' || c == '\t'
+}
+
+/// Split a command line the way the MSVC runtime and `CommandLineToArgvW` do.
+///
+/// The program name is taken verbatim up to the closing quote or the first blank.
+/// In the remaining arguments `2n` backslashes followed by a quote produce `n` backslashes
+/// and toggle quoting, `2n + 1` backslashes followed by a quote produce `n` backslashes and
+/// a literal quote, and `""` inside a quoted section produces a literal quote.
+fn split(command_line: &str) -> Vec<Arg> {
+    let chars = command_line.char_indices().collect::<Vec<_>>();
+    let offset = |i: usize| chars.get(i).map_or(command_line.len(), |(x, _)| *x);
+    let mut args = Vec::new();
+
+    // The program name.
+    let mut i = 0;
+    let mut value = String::new();
+    if chars.first().map(|(_, c)| *c) == Some('"') {
+        i += 1;
+        while i < chars.len() && chars[i].1 != '"' {
+            value.push(chars[i].1);
+            i += 1;
+        }
+        // Skip the closing quote.
+        i = (i + 1).min(chars.len());
+    } else {
+        while i < chars.len() && !is_blank(chars[i].1) {
+            value.push(chars[i].1);
+            i += 1;
+        }
+    }
+    args.push(Arg { value, start: 0, end: offset(i) });
+
+    loop {
+        while i < chars.len() && is_blank(chars[i].1) {
+            i += 1;
+        }
+        if i >= chars.len() {
+            break;
+        }
+
+        let start = i;
+        let mut value = String::new();
+        let mut in_quotes = false;
+        let mut backslashes = 0;
+
+        while i < chars.len() {
+            let c = chars[i].1;
+            match c {
+                '\\' => backslashes += 1,
+                '"' => {
+                    value.extend(std::iter::repeat_n('\\', backslashes / 2));
+                    if backslashes % 2 == 1 {
+                        value.push('"');
+                    } else if in_quotes && chars.get(i + 1).map(|(_, c)| *c) == Some('"') {
+                        value.push('"');
+                        i += 1;
+                    } else {
+                        in_quotes = !in_quotes;
+                    }
+                    backslashes = 0;
+                }
+                _ => {
+                    value.extend(std::iter::repeat_n('\\', backslashes));
+                    backslashes = 0;
+                    if is_blank(c) && !in_quotes {
+                        break;
+                    }
+                    value.push(c);
+                }
+            }
+            i += 1;
+        }
+        value.extend(std::iter::repeat_n('\\', backslashes));
+
+        args.push(Arg { value, start: offset(start), end: offset(i) });
+    }
+
+    args
+}
+
+/// Split a command line into its arguments, including the program name.
+pub fn split_args(command_line: &str) -> Vec<String> {
+    split(command_line).into_iter().map(|x| x.value).collect()
+}
+
+/// Remove shimloader options and their values from a command line.
+///
+/// Every other argument is kept exactly as written, including its quoting and the
+/// whitespace in front of it. Options after a bare `--` are left alone.
+pub fn strip_shim_args(command_line: &str) -> String {
+    let args = split(command_line);
+    let mut removed = vec![false; args.len()];
+
+    let mut i = 1;
+    while i < args.len() {
+        let Some(option) = args[i].value.strip_prefix("--") else {
+            i += 1;
+            continue;
+        };
+
+        if option.is_empty() {
+            break;
+        }
+
+        let (name, inline_value) = match option.split_once('=') {
+            Some((name, _)) => (name, true),
+            None => (option, false),
+        };
+
+        if SHIM_OPTIONS.contains(&name) {
+            removed[i] = true;
+            if !inline_value && !SHIM_FLAGS.contains(&name) && i + 1 < args.len() {
+                removed[i + 1] = true;
+                i += 1;
+            }
+        }
+        i += 1;
+    }
+
+    if !removed.contains(&true) {
+        return command_line.to_string();
+    }
+
+    // Kept arguments bring the whitespace in front of them along, removed ones drop it.
+    let mut result = command_line[..args[0].end].to_string();
+    for (i, arg) in args.iter().enumerate().skip(1) {
+        if !removed[i] {
+            result.push_str(&command_line[args[i - 1].end..arg.end]);
+        }
+    }
+    if let Some(last) = args.last() {
+        result.push_str(&command_line[last.end..]);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_program_name_with_spaces() {
+        let args = split_args("\"C:\\Program Files\\Game\\Game.exe\" -log");
+        assert_eq!(args, vec!["C:\\Program Files\\Game\\Game.exe", "-log"]);
+    }
+
+    #[test]
+    fn test_split_program_name_keeps_backslashes() {
+        let args = split_args("C:\\Game\\Game.exe\\ a");
+        assert_eq!(args, vec!["C:\\Game\\Game.exe\\", "a"]);
+    }
+
+    #[test]
+    fn test_split_quoted_argument() {
+        let args = split_args("game.exe --mod-dir \"D:\\My Mods\" -log");
+        assert_eq!(args, vec!["game.exe", "--mod-dir", "D:\\My Mods", "-log"]);
+    }
+
+    #[test]
+    fn test_split_backslashes_before_quote() {
+        // 2n backslashes before a quote: n backslashes, quote toggles.
+        assert_eq!(split_args("a \"D:\\Mods\\\\\" b"), vec!["a", "D:\\Mods\\", "b"]);
+        // 2n + 1 backslashes before a quote: n backslashes and a literal quote.
+        assert_eq!(split_args("a x\\\"y"), vec!["a", "x\"y"]);
+        // Backslashes not followed by a quote are literal.
+        assert_eq!(split_args("a C:\\\\x\\y"), vec!["a", "C:\\\\x\\y"]);
+    }
+
+    #[test]
+    fn test_split_doubled_quote_inside_quotes() {
+        assert_eq!(split_args("a \"x\"\"y\""), vec!["a", "x\"y"]);
+    }
+
+    #[test]
+    fn test_split_quotes_inside_argument() {
+        assert_eq!(split_args("a --map=Saved=\"D:\\My Saves\""), vec!["a", "--map=Saved=D:\\My Saves"]);
+    }
+
+    #[test]
+    fn test_split_tabs_and_repeated_blanks() {
+        assert_eq!(split_args("a\t b  \t c "), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_strip_nothing_to_strip() {
+        let command_line = "\"C:\\Game\\Game.exe\"  -log   \"-foo bar\"";
+        assert_eq!(strip_shim_args(command_line), command_line);
+    }
+
+    #[test]
+    fn test_strip_separate_values() {
+        let command_line = "game.exe --mod-dir D:\\Mods -log --pak-dir D:\\Paks";
+        assert_eq!(strip_shim_args(command_line), "game.exe -log");
+    }
+
+    #[test]
+    fn test_strip_quoted_values() {
+        let command_line = "\"C:\\Game\\Game.exe\" --mod-dir \"D:\\My Mods\\\\\" -windowed \"-ResX=1280\"";
+        assert_eq!(strip_shim_args(command_line), "\"C:\\Game\\Game.exe\" -windowed \"-ResX=1280\"");
+    }
+
+    #[test]
+    fn test_strip_inline_values() {
+        let command_line = "game.exe --map=Saved=\"D:\\My Saves\" -log --shim-log-level=info";
+        assert_eq!(strip_shim_args(command_line), "game.exe -log");
+    }
+
+    #[test]
+    fn test_strip_quoted_option_name() {
+        let command_line = "game.exe \"--cfg-dir\" D:\\Config -log";
+        assert_eq!(strip_shim_args(command_line), "game.exe -log");
+    }
+
+    #[test]
+    fn test_strip_value_that_looks_like_an_option() {
+        let command_line = "game.exe --mod-dir --pak-dir -log";
+        assert_eq!(strip_shim_args(command_line), "game.exe -log");
+    }
+
+    #[test]
+    fn test_strip_flag_keeps_next_argument() {
+        let command_line = "game.exe --generate-mod-list -log --mod-dir D:\\Mods";
+        assert_eq!(strip_shim_args(command_line), "game.exe -log");
+    }
+
+    #[test]
+    fn test_strip_trailing_option_without_value() {
+        let command_line = "game.exe -log --mod-dir";
+        assert_eq!(strip_shim_args(command_line), "game.exe -log");
+    }
+
+    #[test]
+    fn test_strip_keeps_unknown_long_options() {
+        let command_line = "game.exe --mod-dirs x --map y";
+        assert_eq!(strip_shim_args(command_line), "game.exe --mod-dirs x");
+    }
+
+    #[test]
+    fn test_strip_stops_at_double_dash() {
+        let command_line = "game.exe --mod-dir a -- --mod-dir b";
+        assert_eq!(strip_shim_args(command_line), "game.exe -- --mod-dir b");
+    }
+
+    #[test]
+    fn test_strip_escaped_quote_does_not_end_value() {
+        let command_line = "game.exe --mod-dir \"D:\\\"odd name\" -log";
+        assert_eq!(strip_shim_args(command_line), "game.exe -log");
+    }
+}
@@ -0,0 +1,584 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::{fs, io};
+
+use log::LevelFilter;
+use serde::Deserialize;
+
+use crate::ue4ss::SettingOverride;
+
+/// The name of the configuration file looked up next to the game executable.
+pub const CONFIG_FILE_NAME: &str = "shimloader.toml";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse(toml::de::Error),
+    Invalid(String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => write!(f, "Failed to read {}: {e}", path.display()),
+            ConfigError::Parse(e) => write!(f, "Failed to parse configuration: {e}"),
+            ConfigError::Invalid(reason) => write!(f, "Invalid configuration: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// What to do when the shim fails to start.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorPolicy {
+    /// Show the error and terminate the game.
+    Abort,
+    /// Show the error and start the game without mods.
+    #[default]
+    Warn,
+    /// Log the error and start the game without mods.
+    Silent,
+}
+
+impl FromStr for ErrorPolicy {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "abort" => Ok(ErrorPolicy::Abort),
+            "warn" => Ok(ErrorPolicy::Warn),
+            "silent" => Ok(ErrorPolicy::Silent),
+            _ => Err(ConfigError::Invalid(format!("unknown error policy {s:?}"))),
+        }
+    }
+}
+
+/// A virtual source backed by one or more real directories, or hidden altogether.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MappingConfig {
+    /// The virtual path. Relative sources are resolved against the game root by the caller.
+    pub source: PathBuf,
+    /// Empty for hidden mappings.
+    pub targets: Vec<PathBuf>,
+    /// Index into `targets` of the layer that receives newly created files.
+    pub writable: usize,
+    /// Make the source look absent instead of redirecting it.
+    pub hide: bool,
+    /// Leave files in the original directory until they are written to, then copy them into
+    /// the writable layer.
+    pub copy_on_write: bool,
+}
+
+/// Resolve a directory given on the command line against `launch_dir`, the directory the game was
+/// launched from. Absolute directories are kept as they are.
+pub fn resolve_dir_argument(dir: &str, launch_dir: &Path) -> PathBuf {
+    launch_dir.join(dir)
+}
+
+impl MappingConfig {
+    /// Parse a `<virtual>=<real>` mapping as given to `--map`, resolving a relative target
+    /// against `base_dir`. Returns None unless both sides are non-empty.
+    pub fn parse_map(spec: &str, base_dir: &Path) -> Option<MappingConfig> {
+        let (source, target) = spec.split_once('=')?;
+        if source.is_empty() || target.is_empty() {
+            return None;
+        }
+
+        Some(MappingConfig {
+            source: PathBuf::from(source),
+            targets: vec![resolve_dir_argument(target, base_dir)],
+            writable: 0,
+            hide: false,
+            copy_on_write: false,
+        })
+    }
+}
+
+/// Which groups of hooks get enabled.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HookToggles {
+    pub create_file: bool,
+    pub nt_create_file: bool,
+    pub file_attributes: bool,
+    pub find_file: bool,
+    pub load_library: bool,
+    pub add_dll_directory: bool,
+    /// `CreateDirectoryW`, `RemoveDirectoryW`, `DeleteFileW` and the other calls that change the file system.
+    pub mutation: bool,
+    /// `GetFinalPathNameByHandleW` and `GetFullPathNameW`, which report virtual paths instead of redirect targets.
+    pub path_names: bool,
+    /// `SetCurrentDirectoryW` and `GetCurrentDirectoryW`, which keep a virtual current directory
+    /// so relative paths inside mapped folders are remapped too.
+    pub current_directory: bool,
+    /// `fopen` from ucrtbase, used by code that never goes through the Windows file APIs directly.
+    pub fopen: bool,
+    /// Hide shimloader arguments from `GetCommandLineW`/`GetCommandLineA` and the CRT's argv.
+    pub command_line: bool,
+}
+
+impl Default for HookToggles {
+    fn default() -> Self {
+        HookToggles {
+            // NtCreateFile already covers CreateFileW, so the latter stays off unless asked for.
+            create_file: false,
+            nt_create_file: true,
+            file_attributes: true,
+            find_file: true,
+            load_library: true,
+            add_dll_directory: true,
+            mutation: true,
+            path_names: true,
+            current_directory: true,
+            fopen: true,
+            command_line: true,
+        }
+    }
+}
+
+/// Shimloader settings, read from `shimloader.toml` and overridden by the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    pub log_level: LevelFilter,
+    pub on_error: ErrorPolicy,
+    /// DLLs loaded once the hooks are in place. Relative paths are resolved against the executable's directory.
+    pub load: Vec<PathBuf>,
+    pub mod_dirs: Vec<PathBuf>,
+    /// Serve a `Mods\mods.txt` listing every mod found in `mod_dirs`, on top of the user's own.
+    /// Off unless `generate_mod_list = true` or `--generate-mod-list` is given, since it changes
+    /// which mods UE4SS loads.
+    pub generate_mod_list: bool,
+    pub pak_dirs: Vec<PathBuf>,
+    pub cfg_dirs: Vec<PathBuf>,
+    pub mappings: Vec<MappingConfig>,
+    /// Paths or globs kept out of every mapping. Relative patterns are resolved against the game root by the caller.
+    pub exclude: Vec<PathBuf>,
+    /// `Section.Key=Value` overrides applied to the `UE4SS-settings.ini` the game sees.
+    pub ue4ss_settings: Vec<SettingOverride>,
+    pub hooks: HookToggles,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            log_level: LevelFilter::Debug,
+            on_error: ErrorPolicy::default(),
+            load: vec![PathBuf::from("ue4ss.dll")],
+            mod_dirs: Vec::new(),
+            generate_mod_list: false,
+            pak_dirs: Vec::new(),
+            cfg_dirs: Vec::new(),
+            mappings: Vec::new(),
+            exclude: Vec::new(),
+            ue4ss_settings: Vec::new(),
+            hooks: HookToggles::default(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawConfig {
+    log_level: Option<String>,
+    #[serde(default)]
+    on_error: ErrorPolicy,
+    load: Option<Vec<PathBuf>>,
+    #[serde(default)]
+    mod_dirs: Vec<PathBuf>,
+    generate_mod_list: Option<bool>,
+    #[serde(default)]
+    pak_dirs: Vec<PathBuf>,
+    #[serde(default)]
+    cfg_dirs: Vec<PathBuf>,
+    #[serde(default, rename = "mapping")]
+    mappings: Vec<RawMapping>,
+    #[serde(default)]
+    exclude: Vec<PathBuf>,
+    #[serde(default)]
+    ue4ss_settings: Vec<String>,
+    #[serde(default)]
+    hooks: HookToggles,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawMapping {
+    source: PathBuf,
+    target: Option<PathBuf>,
+    #[serde(default)]
+    targets: Vec<PathBuf>,
+    #[serde(default)]
+    writable: usize,
+    #[serde(default)]
+    hide: bool,
+    #[serde(default)]
+    copy_on_write: bool,
+}
+
+impl Config {
+    /// Load the configuration file at `path`.
+    /// Relative targets and directories are resolved against the directory containing the file.
+    pub fn load(path: &Path) -> Result<Config, ConfigError> {
+        let text = fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+        let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+        Config::parse(&text, base_dir)
+    }
+
+    /// Parse and validate a configuration, resolving relative targets and directories against `base_dir`.
+    pub fn parse(text: &str, base_dir: &Path) -> Result<Config, ConfigError> {
+        let raw: RawConfig = toml::from_str(text).map_err(ConfigError::Parse)?;
+        let defaults = Config::default();
+
+        let log_level = match raw.log_level {
+            Some(level) => LevelFilter::from_str(&level)
+                .map_err(|_| ConfigError::Invalid(format!("unknown log level {level:?}")))?,
+            None => defaults.log_level,
+        };
+
+        let resolve = |paths: Vec<PathBuf>| paths.into_iter().map(|x| base_dir.join(x)).collect::<Vec<_>>();
+
+        let mappings = raw
+            .mappings
+            .into_iter()
+            .map(|mapping| {
+                if mapping.source.as_os_str().is_empty() {
+                    return Err(ConfigError::Invalid("mapping has an empty source".to_string()));
+                }
+
+                if mapping.hide {
+                    if mapping.target.is_some() || !mapping.targets.is_empty() || mapping.copy_on_write {
+                        return Err(ConfigError::Invalid(format!(
+                            "mapping for {} hides its source but also has a target",
+                            mapping.source.display()
+                        )));
+                    }
+
+                    return Ok(MappingConfig {
+                        source: mapping.source,
+                        targets: Vec::new(),
+                        writable: 0,
+                        hide: true,
+                        copy_on_write: false,
+                    });
+                }
+
+                let targets = match (mapping.target, mapping.targets.is_empty()) {
+                    (Some(target), true) => vec![target],
+                    (None, false) => mapping.targets,
+                    (Some(_), false) => {
+                        return Err(ConfigError::Invalid(format!(
+                            "mapping for {} sets both `target` and `targets`",
+                            mapping.source.display()
+                        )))
+                    }
+                    (None, true) => {
+                        return Err(ConfigError::Invalid(format!(
+                            "mapping for {} has no target",
+                            mapping.source.display()
+                        )))
+                    }
+                };
+
+                if mapping.writable >= targets.len() {
+                    return Err(ConfigError::Invalid(format!(
+                        "mapping for {} has writable layer {} but only {} targets",
+                        mapping.source.display(),
+                        mapping.writable,
+                        targets.len()
+                    )));
+                }
+
+                Ok(MappingConfig {
+                    source: mapping.source,
+                    targets: resolve(targets),
+                    writable: mapping.writable,
+                    hide: false,
+                    copy_on_write: mapping.copy_on_write,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let ue4ss_settings = raw
+            .ue4ss_settings
+            .iter()
+            .map(|spec| {
+                SettingOverride::parse(spec).ok_or_else(|| {
+                    ConfigError::Invalid(format!("UE4SS setting {spec:?} is not of the form <section>.<key>=<value>"))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Config {
+            log_level,
+            on_error: raw.on_error,
+            load: raw.load.unwrap_or(defaults.load),
+            mod_dirs: resolve(raw.mod_dirs),
+            generate_mod_list: raw.generate_mod_list.unwrap_or(defaults.generate_mod_list),
+            pak_dirs: resolve(raw.pak_dirs),
+            cfg_dirs: resolve(raw.cfg_dirs),
+            mappings,
+            exclude: raw.exclude,
+            ue4ss_settings,
+            hooks: raw.hooks,
+        })
+    }
+
+    /// Whether the configuration redirects anything at all.
+    pub fn has_mappings(&self) -> bool {
+        [&self.mod_dirs, &self.pak_dirs, &self.cfg_dirs].iter().any(|x| !x.is_empty())
+            || !self.mappings.is_empty()
+            || !self.ue4ss_settings.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> PathBuf {
+        PathBuf::from("C:\\Profile")
+    }
+
+    #[test]
+    fn test_empty_config_uses_defaults() {
+        let config = Config::parse("", &base()).expect("empty config should parse");
+        assert_eq!(config, Config::default());
+        assert!(!config.has_mappings());
+    }
+
+    #[test]
+    fn test_full_config() {
+        let text = r#"
+            log_level = "warn"
+            load = ["ue4ss.dll", "C:\\Tools\\other.dll"]
+            mod_dirs = ["mods"]
+            generate_mod_list = true
+            exclude = ["Binaries\\Win64\\Mods\\shared", "Config\\Default*.ini"]
+            ue4ss_settings = ["General.EnableHotReloadSystem=1"]
+
+            [[mapping]]
+            source = "Saved"
+            target = "saved"
+
+            [[mapping]]
+            source = "Content\\Paks\\LogicMods"
+            targets = ["paks\\a", "paks\\b"]
+            writable = 1
+
+            [hooks]
+            find_file = false
+        "#;
+
+        let config = Config::parse(text, &base()).expect("config should parse");
+        assert_eq!(config.log_level, LevelFilter::Warn);
+        assert_eq!(config.load, vec![PathBuf::from("ue4ss.dll"), PathBuf::from("C:\\Tools\\other.dll")]);
+        assert_eq!(config.mod_dirs, vec![base().join("mods")]);
+        assert!(config.generate_mod_list);
+        assert_eq!(
+            config.mappings,
+            vec![
+                MappingConfig {
+                    source: PathBuf::from("Saved"),
+                    targets: vec![base().join("saved")],
+                    writable: 0,
+                    hide: false,
+                    copy_on_write: false,
+                },
+                MappingConfig {
+                    source: PathBuf::from("Content\\Paks\\LogicMods"),
+                    targets: vec![base().join("paks\\a"), base().join("paks\\b")],
+                    writable: 1,
+                    hide: false,
+                    copy_on_write: false,
+                },
+            ]
+        );
+        assert_eq!(
+            config.exclude,
+            vec![PathBuf::from("Binaries\\Win64\\Mods\\shared"), PathBuf::from("Config\\Default*.ini")]
+        );
+        assert_eq!(
+            config.ue4ss_settings,
+            vec![SettingOverride {
+                section: "General".to_string(),
+                key: "EnableHotReloadSystem".to_string(),
+                value: "1".to_string(),
+            }]
+        );
+        assert!(!config.hooks.find_file);
+        assert!(config.hooks.nt_create_file);
+        assert!(config.has_mappings());
+    }
+
+    #[test]
+    fn test_error_policy() {
+        let config = Config::parse("on_error = \"silent\"", &base()).expect("config should parse");
+        assert_eq!(config.on_error, ErrorPolicy::Silent);
+
+        let result = Config::parse("on_error = \"explode\"", &base());
+        assert!(matches!(result, Err(ConfigError::Parse(_))));
+    }
+
+    #[test]
+    fn test_error_policy_from_str() {
+        assert_eq!(ErrorPolicy::from_str("Abort").ok(), Some(ErrorPolicy::Abort));
+        assert_eq!(ErrorPolicy::from_str("warn").ok(), Some(ErrorPolicy::Warn));
+        assert!(ErrorPolicy::from_str("explode").is_err());
+    }
+
+    #[test]
+    fn test_absolute_target_is_kept() {
+        let text = r#"
+            [[mapping]]
+            source = "Movies"
+            target = "D:\\Movies"
+        "#;
+
+        let config = Config::parse(text, &base()).expect("config should parse");
+        assert_eq!(config.mappings[0].targets, vec![PathBuf::from("D:\\Movies")]);
+    }
+
+    #[test]
+    fn test_map_argument() {
+        let mapping = MappingConfig::parse_map("Saved=Profile\\Saved", &base()).expect("mapping should parse");
+        assert_eq!(mapping.source, PathBuf::from("Saved"));
+        assert_eq!(mapping.targets, vec![base().join("Profile\\Saved")]);
+
+        let mapping = MappingConfig::parse_map("Saved=D:\\Saved", &base()).expect("mapping should parse");
+        assert_eq!(mapping.targets, vec![PathBuf::from("D:\\Saved")]);
+    }
+
+    #[test]
+    fn test_map_argument_with_empty_side() {
+        assert_eq!(MappingConfig::parse_map("=D:\\Saved", &base()), None);
+        assert_eq!(MappingConfig::parse_map("Saved=", &base()), None);
+        assert_eq!(MappingConfig::parse_map("Saved", &base()), None);
+    }
+
+    #[test]
+    fn test_dir_arguments_resolve_like_map_targets() {
+        let mapping = MappingConfig::parse_map("Saved=Profile\\Saved", &base()).expect("mapping should parse");
+        assert_eq!(resolve_dir_argument("Profile\\Saved", &base()), mapping.targets[0]);
+        assert_eq!(resolve_dir_argument("D:\\MyMods", &base()), PathBuf::from("D:\\MyMods"));
+    }
+
+    #[test]
+    fn test_invalid_log_level() {
+        let result = Config::parse("log_level = \"loud\"", &base());
+        assert!(matches!(result, Err(ConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_mapping_without_target() {
+        let text = r#"
+            [[mapping]]
+            source = "Saved"
+        "#;
+
+        let result = Config::parse(text, &base());
+        assert!(matches!(result, Err(ConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_mapping_with_target_and_targets() {
+        let text = r#"
+            [[mapping]]
+            source = "Saved"
+            target = "a"
+            targets = ["b"]
+        "#;
+
+        let result = Config::parse(text, &base());
+        assert!(matches!(result, Err(ConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_writable_out_of_range() {
+        let text = r#"
+            [[mapping]]
+            source = "Saved"
+            targets = ["a", "b"]
+            writable = 2
+        "#;
+
+        let result = Config::parse(text, &base());
+        assert!(matches!(result, Err(ConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_copy_on_write_mapping() {
+        let text = r#"
+            [[mapping]]
+            source = "Saved"
+            target = "saved"
+            copy_on_write = true
+        "#;
+
+        let config = Config::parse(text, &base()).expect("config should parse");
+        assert!(config.mappings[0].copy_on_write);
+        assert_eq!(config.mappings[0].targets, vec![base().join("saved")]);
+    }
+
+    #[test]
+    fn test_hidden_mapping() {
+        let text = r#"
+            [[mapping]]
+            source = "Binaries\\Win64\\Mods\\mods.txt"
+            hide = true
+        "#;
+
+        let config = Config::parse(text, &base()).expect("config should parse");
+        assert_eq!(
+            config.mappings,
+            vec![MappingConfig {
+                source: PathBuf::from("Binaries\\Win64\\Mods\\mods.txt"),
+                targets: Vec::new(),
+                writable: 0,
+                hide: true,
+                copy_on_write: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_hidden_mapping_with_target() {
+        let text = r#"
+            [[mapping]]
+            source = "Content\\Paks\\pakchunk1*.pak"
+            target = "paks"
+            hide = true
+        "#;
+
+        let result = Config::parse(text, &base());
+        assert!(matches!(result, Err(ConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_malformed_ue4ss_setting() {
+        let result = Config::parse("ue4ss_settings = [\"EnableHotReloadSystem=1\"]", &base());
+        assert!(matches!(result, Err(ConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_unknown_key_is_rejected() {
+        let result = Config::parse("mod_dir = \"mods\"", &base());
+        assert!(matches!(result, Err(ConfigError::Parse(_))));
+    }
+
+    #[test]
+    fn test_unknown_hook_is_rejected() {
+        let result = Config::parse("[hooks]\nnot_a_hook = true", &base());
+        assert!(matches!(result, Err(ConfigError::Parse(_))));
+    }
+
+    #[test]
+    fn test_malformed_toml() {
+        let result = Config::parse("log_level = ", &base());
+        assert!(matches!(result, Err(ConfigError::Parse(_))));
+    }
+}
@@ -0,0 +1,56 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::PathBuf;
+
+use crate::config::ConfigError;
+
+/// Everything that can stop the shim from starting up.
+#[derive(Debug)]
+pub enum ShimError {
+    CurrentExe(io::Error),
+    LogFile(io::Error),
+    Args(String),
+    Config(ConfigError),
+    XInputConflict { xinput_path: PathBuf, exe_dir: PathBuf },
+    GameLayout(PathBuf),
+    MissingDll(PathBuf),
+    Hooks(Box<dyn Error>),
+}
+
+impl Display for ShimError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShimError::CurrentExe(e) => {
+                write!(f, "Failed to get the path of the currently running executable. {e}")
+            }
+            ShimError::LogFile(e) => write!(f, "Failed to create log file. {e}"),
+            ShimError::Args(reason) => write!(f, "Failed to parse arguments. {reason}"),
+            ShimError::Config(e) => write!(f, "{e}"),
+            ShimError::XInputConflict { xinput_path, exe_dir } => write!(
+                f,
+                "Shimloader is not compatible with the xinput1_3.dll UE4SS binary.\n
+                1. Remove the file at {} \n
+                2. Ensure that ue4ss.dll exists within {} \n
+                3. Run the game again.",
+                xinput_path.display(),
+                exe_dir.display()
+            ),
+            ShimError::GameLayout(exe) => write!(
+                f,
+                "The executable at {} is not contained within a valid UE directory structure.",
+                exe.display()
+            ),
+            ShimError::MissingDll(path) => write!(f, "{} could not be found.", path.display()),
+            ShimError::Hooks(e) => write!(f, "Failed to enable one or more hooks. {e}"),
+        }
+    }
+}
+
+impl Error for ShimError {}
+
+impl From<ConfigError> for ShimError {
+    fn from(e: ConfigError) -> Self {
+        ShimError::Config(e)
+    }
+}
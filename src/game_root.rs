@@ -0,0 +1,230 @@
+use std::ffi::OsStr;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Which rule identified the game root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RootRule {
+    /// The directory contains the given `.uproject` file.
+    UProject(PathBuf),
+    /// The directory contains both a `Binaries` and a `Content` directory.
+    BinariesAndContent,
+    /// The directory sits next to an `Engine` directory.
+    EngineSibling,
+}
+
+impl Display for RootRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RootRule::UProject(file) => write!(f, "project file {}", file.display()),
+            RootRule::BinariesAndContent => write!(f, "Binaries and Content directories"),
+            RootRule::EngineSibling => write!(f, "sibling of the Engine directory"),
+        }
+    }
+}
+
+/// The project directory of an Unreal game, the one containing `Binaries`, `Content` and `Config`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameRoot {
+    pub path: PathBuf,
+    pub rule: RootRule,
+}
+
+/// Find the game root for the executable at `exe`.
+///
+/// Starting at the executable's directory, each ancestor is checked for a `.uproject` file,
+/// then for a `Binaries` + `Content` pair, then for an `Engine` directory next to the project.
+/// The first ancestor matching any rule wins, which handles `Win64`, `WinGDK` and extra
+/// wrapper folders alike. The `Engine` directory itself is never picked as the game root.
+pub fn find_game_root(exe: &Path) -> Option<GameRoot> {
+    let exe_dir = exe.parent()?;
+
+    for dir in exe_dir.ancestors() {
+        if let Some(project_file) = find_uproject(dir) {
+            return Some(GameRoot {
+                path: dir.to_path_buf(),
+                rule: RootRule::UProject(project_file),
+            });
+        }
+
+        if !is_engine_dir(dir) && dir.join("Binaries").is_dir() && dir.join("Content").is_dir() {
+            return Some(GameRoot {
+                path: dir.to_path_buf(),
+                rule: RootRule::BinariesAndContent,
+            });
+        }
+
+        if dir.join("Engine").is_dir() {
+            if let Some(path) = find_engine_sibling(dir, exe) {
+                return Some(GameRoot {
+                    path,
+                    rule: RootRule::EngineSibling,
+                });
+            }
+        }
+    }
+
+    None
+}
+
+fn is_engine_dir(dir: &Path) -> bool {
+    dir.file_name()
+        .is_some_and(|x| x.eq_ignore_ascii_case("Engine"))
+}
+
+fn find_uproject(dir: &Path) -> Option<PathBuf> {
+    let mut files = fs::read_dir(dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|x| x.path())
+        .filter(|x| x.is_file())
+        .filter(|x| x.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("uproject")))
+        .collect::<Vec<_>>();
+
+    // Sort so the result doesn't depend on directory enumeration order.
+    files.sort();
+    files.into_iter().next()
+}
+
+/// Pick the project directory among the siblings of `<dir>/Engine`.
+///
+/// A sibling that contains the executable wins, then one named after the executable
+/// (`Game` for `Game.exe` or `Game-Win64-Shipping.exe`), then the only sibling with a
+/// `Binaries` directory.
+fn find_engine_sibling(dir: &Path, exe: &Path) -> Option<PathBuf> {
+    let mut candidates = fs::read_dir(dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|x| x.path())
+        .filter(|x| x.is_dir() && !is_engine_dir(x))
+        .filter(|x| x.join("Binaries").is_dir() || x.join("Content").is_dir())
+        .collect::<Vec<_>>();
+    candidates.sort();
+
+    if let Some(containing) = candidates.iter().find(|x| exe.starts_with(x)) {
+        return Some(containing.clone());
+    }
+
+    let exe_name = exe
+        .file_stem()
+        .and_then(OsStr::to_str)
+        .map(|x| x.split('-').next().unwrap_or(x))?;
+
+    if let Some(named) = candidates
+        .iter()
+        .find(|x| x.file_name().and_then(OsStr::to_str).is_some_and(|x| x.eq_ignore_ascii_case(exe_name)))
+    {
+        return Some(named.clone());
+    }
+
+    let mut with_binaries = candidates.into_iter().filter(|x| x.join("Binaries").is_dir());
+    match (with_binaries.next(), with_binaries.next()) {
+        (Some(only), None) => Some(only),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::Fixture;
+
+    #[test]
+    fn test_standard_layout() {
+        let fixture = Fixture::new("standard");
+        fixture.dir("Game/Content/Paks");
+        let exe = fixture.file("Game/Binaries/Win64/Game-Win64-Shipping.exe");
+
+        let root = find_game_root(&exe).expect("root should be found");
+        assert_eq!(root.path, fixture.path("Game"));
+        assert_eq!(root.rule, RootRule::BinariesAndContent);
+    }
+
+    #[test]
+    fn test_wingdk_layout() {
+        let fixture = Fixture::new("wingdk");
+        fixture.dir("Game/Content");
+        let exe = fixture.file("Game/Binaries/WinGDK/Game-WinGDK-Shipping.exe");
+
+        let root = find_game_root(&exe).expect("root should be found");
+        assert_eq!(root.path, fixture.path("Game"));
+    }
+
+    #[test]
+    fn test_extra_wrapper_folder() {
+        let fixture = Fixture::new("wrapper");
+        fixture.dir("Game/Content");
+        let exe = fixture.file("Game/Binaries/Win64/Shipping/Game.exe");
+
+        let root = find_game_root(&exe).expect("root should be found");
+        assert_eq!(root.path, fixture.path("Game"));
+    }
+
+    #[test]
+    fn test_uproject() {
+        let fixture = Fixture::new("uproject");
+        let project = fixture.file("Game/Game.uproject");
+        let exe = fixture.file("Game/Binaries/Win64/Game.exe");
+
+        let root = find_game_root(&exe).expect("root should be found");
+        assert_eq!(root.path, fixture.path("Game"));
+        assert_eq!(root.rule, RootRule::UProject(project));
+    }
+
+    #[test]
+    fn test_launcher_stub_next_to_engine() {
+        let fixture = Fixture::new("stub");
+        fixture.dir("Engine/Binaries");
+        fixture.dir("Engine/Content");
+        fixture.dir("Game/Binaries/Win64");
+        fixture.dir("Game/Content");
+        let exe = fixture.file("Game.exe");
+
+        let root = find_game_root(&exe).expect("root should be found");
+        assert_eq!(root.path, fixture.path("Game"));
+        assert_eq!(root.rule, RootRule::EngineSibling);
+    }
+
+    #[test]
+    fn test_engine_sibling_without_content() {
+        let fixture = Fixture::new("sibling");
+        fixture.dir("Engine");
+        let exe = fixture.file("Game/Binaries/Win64/Game-Win64-Shipping.exe");
+
+        let root = find_game_root(&exe).expect("root should be found");
+        assert_eq!(root.path, fixture.path("Game"));
+        assert_eq!(root.rule, RootRule::EngineSibling);
+    }
+
+    #[test]
+    fn test_stub_picks_sibling_by_name() {
+        let fixture = Fixture::new("byname");
+        fixture.dir("Engine");
+        fixture.dir("Game/Binaries");
+        fixture.dir("Other/Binaries");
+        let exe = fixture.file("Game.exe");
+
+        let root = find_game_root(&exe).expect("root should be found");
+        assert_eq!(root.path, fixture.path("Game"));
+    }
+
+    #[test]
+    fn test_ambiguous_siblings() {
+        let fixture = Fixture::new("ambiguous");
+        fixture.dir("Engine");
+        fixture.dir("A/Binaries");
+        fixture.dir("B/Binaries");
+        let exe = fixture.file("Launcher.exe");
+
+        assert_eq!(find_game_root(&exe), None);
+    }
+
+    #[test]
+    fn test_no_layout() {
+        let fixture = Fixture::new("none");
+        let exe = fixture.file("Tools/Game.exe");
+
+        assert_eq!(find_game_root(&exe), None);
+    }
+}
@@ -1,40 +1,66 @@
 use std::collections::HashMap;
-use std::{alloc, mem, ptr, slice};
-use std::alloc::Layout;
+use std::{mem, ptr, slice};
 use std::error::Error;
-use std::ffi::c_void;
+use std::fmt::{Debug, Formatter};
+use std::ffi::{c_void, CStr, CString};
 use std::fs::{File, OpenOptions};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{LazyLock, Mutex, OnceLock, PoisonError};
 
 use log::{debug, error};
-use once_cell::sync::Lazy;
 use retour::static_detour;
-use widestring::{U16CStr, U16CString, WideString};
-use windows_sys::core::PCWSTR;
-use windows_sys::w;
+use widestring::{U16CStr, U16CString};
+use windows_sys::core::{PCSTR, PCWSTR, PWSTR};
+use windows_sys::{s, w};
 use windows_sys::Win32::Foundation::{
+    CloseHandle,
     GetLastError, 
     SetLastError, 
     BOOL, 
+    ERROR_ACCESS_DENIED,
+    ERROR_FILE_NOT_FOUND,
+    ERROR_PATH_NOT_FOUND,
     ERROR_NO_MORE_FILES, 
     FILETIME, 
     HANDLE, 
+    INVALID_HANDLE_VALUE,
     MAX_PATH, 
     NTSTATUS, 
+    STATUS_BUFFER_OVERFLOW,
+    STATUS_INFO_LENGTH_MISMATCH,
+    STATUS_NO_MORE_FILES,
+    STATUS_NO_SUCH_FILE,
+    STATUS_OBJECT_NAME_NOT_FOUND,
+    STATUS_SUCCESS,
     UNICODE_STRING,
-    HMODULE
+    HMODULE,
+    TRUE
+};
+use windows_sys::Win32::Globalization::CP_ACP;
+use windows_sys::Win32::Security::{
+    GetFileSecurityW, SetFileSecurityW, DACL_SECURITY_INFORMATION, SECURITY_ATTRIBUTES
 };
-use windows_sys::Win32::Security::SECURITY_ATTRIBUTES;
 use windows_sys::Win32::Storage::FileSystem::{
-    CreateFileW, FindClose, FindFileHandle, FindFirstFileExW, FindFirstFileW, FindNextFileW, GetFileAttributesExW, GetFileAttributesW, NtCreateFile, FILE_ATTRIBUTE_DIRECTORY, FILE_CREATION_DISPOSITION, FILE_FLAGS_AND_ATTRIBUTES, FILE_SHARE_MODE, FINDEX_INFO_LEVELS, FINDEX_SEARCH_OPS, FIND_FIRST_EX_FLAGS, GET_FILEEX_INFO_LEVELS, NT_CREATE_FILE_DISPOSITION, WIN32_FIND_DATAW
+    CopyFileW, CreateDirectoryW, CreateFileA, CreateFileW, DeleteFileW, FindClose, FindFileHandle, FindFirstFileA, FindFirstFileExW, FindFirstFileW, FindNextFileA, FindNextFileW, GetFileAttributesA, GetFileAttributesExA, GetFileAttributesExW, GetFileAttributesW, GetFinalPathNameByHandleW, GetFullPathNameW, MoveFileExW, NtCreateFile, QueryDosDeviceW, RemoveDirectoryW, ReplaceFileW, FILE_ATTRIBUTE_DIRECTORY, FILE_CREATION_DISPOSITION, FILE_FLAG_BACKUP_SEMANTICS, FILE_LIST_DIRECTORY, FILE_NAME, FILE_OPEN, FILE_SHARE_DELETE, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING, FILE_FLAGS_AND_ATTRIBUTES, FILE_SHARE_MODE, FINDEX_INFO_LEVELS, FINDEX_SEARCH_OPS, FIND_FIRST_EX_FLAGS, GET_FILEEX_INFO_LEVELS, INVALID_FILE_ATTRIBUTES, MOVEFILE_COPY_ALLOWED, MOVEFILE_DELAY_UNTIL_REBOOT, MOVEFILE_REPLACE_EXISTING, MOVE_FILE_FLAGS, NT_CREATE_FILE_DISPOSITION, REPLACE_FILE_FLAGS, REPLACEFILE_IGNORE_ACL_ERRORS, REPLACEFILE_IGNORE_MERGE_ERRORS, SetFileAttributesW, WIN32_FIND_DATAA, WIN32_FIND_DATAW
+};
+use windows_sys::Win32::System::Environment::{
+    GetCommandLineA, GetCommandLineW, GetCurrentDirectoryW, SetCurrentDirectoryW
+};
+use windows_sys::Win32::System::LibraryLoader::{
+    LoadLibraryA, LoadLibraryExA, LoadLibraryExW, LoadLibraryW, AddDllDirectory, GetModuleHandleW, GetProcAddress,
+    LOAD_LIBRARY_FLAGS
 };
-use windows_sys::Win32::System::LibraryLoader::{LoadLibraryW, AddDllDirectory};
 use windows_sys::Win32::System::WindowsProgramming::{
+    NtClose,
+    NtOpenFile,
     IO_STATUS_BLOCK,
     IO_STATUS_BLOCK_0,
     OBJECT_ATTRIBUTES
 };
+use crate::{cmdline, nt};
+use crate::config::HookToggles;
 use crate::paths::{self, NormalizedPath, remap_path};
 
 
@@ -63,8 +89,74 @@ static_detour! {
         u32
     ) -> NTSTATUS;
 
+    pub static NtOpenFile_Detour: unsafe extern "system" fn(
+        *mut HANDLE,
+        u32,
+        *mut OBJECT_ATTRIBUTES,
+        *mut IO_STATUS_BLOCK,
+        u32,
+        u32
+    ) -> NTSTATUS;
+
+    pub static NtQueryAttributesFile_Detour: unsafe extern "system" fn(
+        *mut OBJECT_ATTRIBUTES,
+        *mut c_void
+    ) -> NTSTATUS;
+
+    pub static NtQueryFullAttributesFile_Detour: unsafe extern "system" fn(
+        *mut OBJECT_ATTRIBUTES,
+        *mut c_void
+    ) -> NTSTATUS;
+
+    pub static NtQueryDirectoryFile_Detour: unsafe extern "system" fn(
+        HANDLE,
+        HANDLE,
+        *const c_void,
+        *const c_void,
+        *mut IO_STATUS_BLOCK,
+        *mut c_void,
+        u32,
+        u32,
+        u8,
+        *mut UNICODE_STRING,
+        u8
+    ) -> NTSTATUS;
+
+    pub static NtQueryDirectoryFileEx_Detour: unsafe extern "system" fn(
+        HANDLE,
+        HANDLE,
+        *const c_void,
+        *const c_void,
+        *mut IO_STATUS_BLOCK,
+        *mut c_void,
+        u32,
+        u32,
+        u32,
+        *mut UNICODE_STRING
+    ) -> NTSTATUS;
+
+    pub static NtClose_Detour: unsafe extern "system" fn(HANDLE) -> NTSTATUS;
+
+    pub static CreateFileA_Detour: unsafe extern "system" fn(
+        PCSTR,
+        u32,
+        FILE_SHARE_MODE,
+        *const SECURITY_ATTRIBUTES,
+        FILE_CREATION_DISPOSITION,
+        FILE_FLAGS_AND_ATTRIBUTES,
+        HANDLE
+    ) -> HANDLE;
+
     pub static GetFileAttributesW_Detour: unsafe extern "system" fn(PCWSTR) -> u32;
 
+    pub static GetFileAttributesA_Detour: unsafe extern "system" fn(PCSTR) -> u32;
+
+    pub static GetFileAttributesExA_Detour: unsafe extern "system" fn(
+        PCSTR,
+        GET_FILEEX_INFO_LEVELS,
+        *mut c_void
+    ) -> BOOL;
+
     pub static GetFileAttributesExW_Detour: unsafe extern "system" fn(
         PCWSTR,
         GET_FILEEX_INFO_LEVELS,
@@ -92,13 +184,68 @@ static_detour! {
 
     pub static FindClose_Detour: unsafe extern "system" fn(HANDLE) -> BOOL;
 
+    pub static FindFirstFileA_Detour: unsafe extern "system" fn(
+        PCSTR,
+        *mut WIN32_FIND_DATAA
+    ) -> FindFileHandle;
+
+    pub static FindNextFileA_Detour: unsafe extern "system" fn(
+        FindFileHandle,
+        *mut WIN32_FIND_DATAA
+    ) -> BOOL;
+
+    pub static fopen_Detour: unsafe extern "C" fn(PCSTR, PCSTR) -> *mut c_void;
+
+    pub static CreateDirectoryW_Detour: unsafe extern "system" fn(
+        PCWSTR,
+        *const SECURITY_ATTRIBUTES
+    ) -> BOOL;
+
+    pub static RemoveDirectoryW_Detour: unsafe extern "system" fn(PCWSTR) -> BOOL;
+
+    pub static DeleteFileW_Detour: unsafe extern "system" fn(PCWSTR) -> BOOL;
+
+    pub static MoveFileExW_Detour: unsafe extern "system" fn(PCWSTR, PCWSTR, MOVE_FILE_FLAGS) -> BOOL;
+
+    pub static CopyFileW_Detour: unsafe extern "system" fn(PCWSTR, PCWSTR, BOOL) -> BOOL;
+
+    pub static ReplaceFileW_Detour: unsafe extern "system" fn(
+        PCWSTR,
+        PCWSTR,
+        PCWSTR,
+        REPLACE_FILE_FLAGS,
+        *const c_void,
+        *const c_void
+    ) -> BOOL;
+
+    pub static GetFinalPathNameByHandleW_Detour: unsafe extern "system" fn(HANDLE, PWSTR, u32, FILE_NAME) -> u32;
+
+    pub static GetFullPathNameW_Detour: unsafe extern "system" fn(PCWSTR, u32, PWSTR, *mut PWSTR) -> u32;
+
+    pub static SetCurrentDirectoryW_Detour: unsafe extern "system" fn(PCWSTR) -> BOOL;
+
+    pub static GetCurrentDirectoryW_Detour: unsafe extern "system" fn(u32, PWSTR) -> u32;
+
     pub static LoadLibraryW_Detour: unsafe extern "system" fn(PCWSTR) -> HMODULE;
 
+    pub static LoadLibraryExW_Detour: unsafe extern "system" fn(PCWSTR, HANDLE, LOAD_LIBRARY_FLAGS) -> HMODULE;
+
+    pub static LoadLibraryA_Detour: unsafe extern "system" fn(PCSTR) -> HMODULE;
+
+    pub static LoadLibraryExA_Detour: unsafe extern "system" fn(PCSTR, HANDLE, LOAD_LIBRARY_FLAGS) -> HMODULE;
+
     pub static AddDllDirectory_Detour: unsafe extern "system" fn(PCWSTR) -> *mut c_void;
+
+    pub static GetCommandLineW_Detour: unsafe extern "system" fn() -> PCWSTR;
+
+    pub static GetCommandLineA_Detour: unsafe extern "system" fn() -> PCSTR;
 }
 
 
-pub unsafe fn enable_hooks() -> Result<(), Box<dyn Error>> {
+/// Initialize every detour, then enable the ones selected by `toggles`.
+/// Disabled detours are still initialized so that the shim can call the original functions through them.
+#[allow(clippy::too_many_lines)]
+pub unsafe fn enable_hooks(toggles: &HookToggles) -> Result<(), Box<dyn Error>> {
     CreateFileW_Detour.initialize(CreateFileW, |a, b, c, d, e, f, g| unsafe {
         createfilew_detour(
             a,
@@ -125,36 +272,402 @@ pub unsafe fn enable_hooks() -> Result<(), Box<dyn Error>> {
             j,
             k,
         )
-    })?.enable()?;
+    })?;
+
+    NtOpenFile_Detour.initialize(NtOpenFile, |a, b, c, d, e, f| unsafe {
+        ntopenfile_detour(a, b, c, d, e, f)
+    })?;
+
+    // These aren't exported by the import libraries, so they are looked up in ntdll directly.
+    let query_attributes: unsafe extern "system" fn(*mut OBJECT_ATTRIBUTES, *mut c_void) -> NTSTATUS =
+        mem::transmute(ntdll_export(s!("NtQueryAttributesFile"))?);
+    NtQueryAttributesFile_Detour.initialize(query_attributes, |a, b| unsafe {
+        ntqueryattributesfile_detour(a, b)
+    })?;
+
+    let query_full_attributes: unsafe extern "system" fn(*mut OBJECT_ATTRIBUTES, *mut c_void) -> NTSTATUS =
+        mem::transmute(ntdll_export(s!("NtQueryFullAttributesFile"))?);
+    NtQueryFullAttributesFile_Detour.initialize(query_full_attributes, |a, b| unsafe {
+        ntqueryfullattributesfile_detour(a, b)
+    })?;
+
+    let query_directory: unsafe extern "system" fn(
+        HANDLE,
+        HANDLE,
+        *const c_void,
+        *const c_void,
+        *mut IO_STATUS_BLOCK,
+        *mut c_void,
+        u32,
+        u32,
+        u8,
+        *mut UNICODE_STRING,
+        u8
+    ) -> NTSTATUS = mem::transmute(ntdll_export(s!("NtQueryDirectoryFile"))?);
+    NtQueryDirectoryFile_Detour.initialize(query_directory, |a, b, c, d, e, f, g, h, i, j, k| unsafe {
+        ntquerydirectoryfile_detour(a, b, c, d, e, f, g, h, i, j, k)
+    })?;
+
+    // NtQueryDirectoryFileEx only exists on Windows 10 1709 and later.
+    let query_directory_ex_hooked = match ntdll_export(s!("NtQueryDirectoryFileEx")) {
+        Ok(query_directory_ex) => {
+            let query_directory_ex: unsafe extern "system" fn(
+                HANDLE,
+                HANDLE,
+                *const c_void,
+                *const c_void,
+                *mut IO_STATUS_BLOCK,
+                *mut c_void,
+                u32,
+                u32,
+                u32,
+                *mut UNICODE_STRING
+            ) -> NTSTATUS = mem::transmute(query_directory_ex);
+            NtQueryDirectoryFileEx_Detour.initialize(query_directory_ex, |a, b, c, d, e, f, g, h, i, j| unsafe {
+                ntquerydirectoryfileex_detour(a, b, c, d, e, f, g, h, i, j)
+            })?;
+            true
+        }
+        Err(_) => false,
+    };
+
+    NtClose_Detour.initialize(NtClose, |a| unsafe {
+        ntclose_detour(a)
+    })?;
+
+    CreateFileA_Detour.initialize(CreateFileA, |a, b, c, d, e, f, g| unsafe {
+        createfilea_detour(a, b, c, d, e, f, g)
+    })?;
 
     GetFileAttributesW_Detour.initialize(GetFileAttributesW, |a| unsafe {
         getfileattributesw_detour(a)
-    })?.enable()?;
+    })?;
 
     GetFileAttributesExW_Detour.initialize(GetFileAttributesExW, |a, b, c| unsafe {
         getfileattributesexw_detour(a, b, c)
-    })?.enable()?;
+    })?;
+
+    GetFileAttributesA_Detour.initialize(GetFileAttributesA, |a| unsafe {
+        getfileattributesa_detour(a)
+    })?;
+
+    GetFileAttributesExA_Detour.initialize(GetFileAttributesExA, |a, b, c| unsafe {
+        getfileattributesexa_detour(a, b, c)
+    })?;
 
     FindFirstFileW_Detour.initialize(FindFirstFileW, |a, b| unsafe {
         findfirstfilew_detour(a, b)
-    })?.enable()?;
+    })?;
 
     FindFirstFileExW_Detour.initialize(FindFirstFileExW, |a, b, c, d, e, f| unsafe {
         findfirstfileexw_detour(a, b, c, d, e, f)
-    })?.enable()?;
+    })?;
+
+    FindNextFileW_Detour.initialize(FindNextFileW, |a, b| unsafe {
+        findnextfilew_detour(a, b)
+    })?;
+
+    FindClose_Detour.initialize(FindClose, |a| unsafe {
+        findclose_detour(a)
+    })?;
+
+    FindFirstFileA_Detour.initialize(FindFirstFileA, |a, b| unsafe {
+        findfirstfilea_detour(a, b)
+    })?;
+
+    FindNextFileA_Detour.initialize(FindNextFileA, |a, b| unsafe {
+        findnextfilea_detour(a, b)
+    })?;
+
+    CreateDirectoryW_Detour.initialize(CreateDirectoryW, |a, b| unsafe {
+        createdirectoryw_detour(a, b)
+    })?;
+
+    RemoveDirectoryW_Detour.initialize(RemoveDirectoryW, |a| unsafe {
+        removedirectoryw_detour(a)
+    })?;
+
+    DeleteFileW_Detour.initialize(DeleteFileW, |a| unsafe {
+        deletefilew_detour(a)
+    })?;
+
+    MoveFileExW_Detour.initialize(MoveFileExW, |a, b, c| unsafe {
+        movefileexw_detour(a, b, c)
+    })?;
+
+    CopyFileW_Detour.initialize(CopyFileW, |a, b, c| unsafe {
+        copyfilew_detour(a, b, c)
+    })?;
+
+    ReplaceFileW_Detour.initialize(ReplaceFileW, |a, b, c, d, e, f| unsafe {
+        replacefilew_detour(a, b, c, d, e, f)
+    })?;
+
+    GetFinalPathNameByHandleW_Detour.initialize(GetFinalPathNameByHandleW, |a, b, c, d| unsafe {
+        getfinalpathnamebyhandlew_detour(a, b, c, d)
+    })?;
+
+    GetFullPathNameW_Detour.initialize(GetFullPathNameW, |a, b, c, d| unsafe {
+        getfullpathnamew_detour(a, b, c, d)
+    })?;
+
+    SetCurrentDirectoryW_Detour.initialize(SetCurrentDirectoryW, |a| unsafe {
+        setcurrentdirectoryw_detour(a)
+    })?;
+
+    GetCurrentDirectoryW_Detour.initialize(GetCurrentDirectoryW, |a, b| unsafe {
+        getcurrentdirectoryw_detour(a, b)
+    })?;
+
+    // fopen lives in the CRT, which is only hooked if the game uses the shared ucrtbase.
+    let fopen_hooked = initialize_fopen_detour()?;
 
     LoadLibraryW_Detour.initialize(LoadLibraryW, |lpfilename| unsafe {
         loadlibraryw_detour(lpfilename)
-    })?.enable()?;
+    })?;
+
+    LoadLibraryExW_Detour.initialize(LoadLibraryExW, |a, b, c| unsafe {
+        loadlibraryexw_detour(a, b, c)
+    })?;
+
+    LoadLibraryA_Detour.initialize(LoadLibraryA, |lpfilename| unsafe {
+        loadlibrarya_detour(lpfilename)
+    })?;
+
+    LoadLibraryExA_Detour.initialize(LoadLibraryExA, |a, b, c| unsafe {
+        loadlibraryexa_detour(a, b, c)
+    })?;
 
     AddDllDirectory_Detour.initialize(AddDllDirectory, |lppathnamestr| unsafe {
         adddlldirectory_detour(lppathnamestr) 
-    })?.enable()?;
-    
+    })?;
+
+    if toggles.create_file {
+        CreateFileW_Detour.enable()?;
+        CreateFileA_Detour.enable()?;
+    }
+
+    if toggles.nt_create_file {
+        NtCreateFile_Detour.enable()?;
+        NtOpenFile_Detour.enable()?;
+    }
+
+    if toggles.file_attributes {
+        GetFileAttributesW_Detour.enable()?;
+        GetFileAttributesExW_Detour.enable()?;
+        GetFileAttributesA_Detour.enable()?;
+        GetFileAttributesExA_Detour.enable()?;
+        NtQueryAttributesFile_Detour.enable()?;
+        NtQueryFullAttributesFile_Detour.enable()?;
+    }
+
+    if toggles.find_file {
+        FindFirstFileW_Detour.enable()?;
+        FindFirstFileExW_Detour.enable()?;
+        FindNextFileW_Detour.enable()?;
+        FindClose_Detour.enable()?;
+        FindFirstFileA_Detour.enable()?;
+        FindNextFileA_Detour.enable()?;
+        NtQueryDirectoryFile_Detour.enable()?;
+        NtClose_Detour.enable()?;
+
+        if query_directory_ex_hooked {
+            NtQueryDirectoryFileEx_Detour.enable()?;
+        }
+    }
+
+    if toggles.mutation {
+        CreateDirectoryW_Detour.enable()?;
+        RemoveDirectoryW_Detour.enable()?;
+        DeleteFileW_Detour.enable()?;
+        MoveFileExW_Detour.enable()?;
+        CopyFileW_Detour.enable()?;
+        ReplaceFileW_Detour.enable()?;
+    }
+
+    if toggles.path_names {
+        GetFinalPathNameByHandleW_Detour.enable()?;
+        GetFullPathNameW_Detour.enable()?;
+    }
+
+    if toggles.current_directory {
+        SetCurrentDirectoryW_Detour.enable()?;
+        GetCurrentDirectoryW_Detour.enable()?;
+    }
+
+    if toggles.fopen && fopen_hooked {
+        fopen_Detour.enable()?;
+    }
+
+    if toggles.load_library {
+        LoadLibraryW_Detour.enable()?;
+        LoadLibraryExW_Detour.enable()?;
+        LoadLibraryA_Detour.enable()?;
+        LoadLibraryExA_Detour.enable()?;
+    }
+
+    if toggles.add_dll_directory {
+        AddDllDirectory_Detour.enable()?;
+    }
+
+    Ok(())
+}
+
+/// Disable the file system detours again so the game runs without any redirection.
+/// The command line detours are left alone, shimloader arguments stay hidden either way.
+pub unsafe fn disable_hooks() -> Result<(), Box<dyn Error>> {
+    macro_rules! disable {
+        ($($detour:ident),* $(,)?) => {
+            $(
+                if $detour.is_enabled() {
+                    $detour.disable()?;
+                }
+            )*
+        };
+    }
+
+    disable!(
+        CreateFileW_Detour,
+        CreateFileA_Detour,
+        NtCreateFile_Detour,
+        NtOpenFile_Detour,
+        NtQueryAttributesFile_Detour,
+        NtQueryFullAttributesFile_Detour,
+        NtQueryDirectoryFile_Detour,
+        NtQueryDirectoryFileEx_Detour,
+        NtClose_Detour,
+        GetFileAttributesW_Detour,
+        GetFileAttributesExW_Detour,
+        GetFileAttributesA_Detour,
+        GetFileAttributesExA_Detour,
+        FindFirstFileW_Detour,
+        FindFirstFileExW_Detour,
+        FindNextFileW_Detour,
+        FindClose_Detour,
+        FindFirstFileA_Detour,
+        FindNextFileA_Detour,
+        fopen_Detour,
+        CreateDirectoryW_Detour,
+        RemoveDirectoryW_Detour,
+        DeleteFileW_Detour,
+        MoveFileExW_Detour,
+        CopyFileW_Detour,
+        ReplaceFileW_Detour,
+        GetFinalPathNameByHandleW_Detour,
+        GetFullPathNameW_Detour,
+        SetCurrentDirectoryW_Detour,
+        GetCurrentDirectoryW_Detour,
+        LoadLibraryW_Detour,
+        LoadLibraryExW_Detour,
+        LoadLibraryA_Detour,
+        LoadLibraryExA_Detour,
+        AddDllDirectory_Detour,
+    );
+
+    Ok(())
+}
+
+/// The command line handed to the game once shimloader arguments have been removed.
+static COMMAND_LINE_W: OnceLock<U16CString> = OnceLock::new();
+static COMMAND_LINE_A: OnceLock<CString> = OnceLock::new();
+
+/// Hide shimloader arguments from the game.
+///
+/// `GetCommandLineW` and `GetCommandLineA` are detoured to return the stripped command line.
+/// ucrtbase caches the command line before the shim gets a chance to hook anything, so its
+/// copies are patched in place as well.
+pub unsafe fn hide_shim_args() -> Result<(), Box<dyn Error>> {
+    let original = U16CStr::from_ptr_str(GetCommandLineW()).to_string()?;
+    let stripped = cmdline::strip_shim_args(&original);
+    if stripped == original {
+        return Ok(());
+    }
+
+    debug!("[hide_shim_args] {original:?} to {stripped:?}");
+
+    let wide = COMMAND_LINE_W.get_or_init(|| U16CString::from_str_truncate(&stripped));
+    GetCommandLineW_Detour.initialize(GetCommandLineW, || wide.as_ptr())?.enable()?;
+
+    // The ANSI command line is left alone if it can't be represented in the active code page.
+    let ansi = paths::encode_ansi(wide.as_slice(), CP_ACP)
+        .and_then(|x| CString::new(x).ok())
+        .map(|x| COMMAND_LINE_A.get_or_init(|| x));
+    if let Some(ansi) = ansi {
+        GetCommandLineA_Detour.initialize(GetCommandLineA, || ansi.as_ptr().cast())?.enable()?;
+    }
+
+    patch_crt_command_line(&stripped, wide, ansi);
 
     Ok(())
 }
 
+/// Call one of ucrtbase's `__p_*` accessors, which return a pointer to a CRT global.
+unsafe fn crt_global<T>(ucrt: HMODULE, name: PCSTR) -> Option<*mut T> {
+    let accessor = GetProcAddress(ucrt, name)?;
+    let accessor: unsafe extern "C" fn() -> *mut T = mem::transmute(accessor);
+
+    Some(accessor()).filter(|x| !x.is_null())
+}
+
+/// Point the CRT's cached command line, and its argv if it has already been built, at the stripped versions.
+unsafe fn patch_crt_command_line(stripped: &str, wide: &'static U16CString, ansi: Option<&'static CString>) {
+    let ucrt = GetModuleHandleW(w!("ucrtbase.dll"));
+    if ucrt == 0 {
+        return;
+    }
+
+    if let Some(wcmdln) = crt_global::<*const u16>(ucrt, s!("__p__wcmdln")) {
+        *wcmdln = wide.as_ptr();
+    }
+
+    if let (Some(acmdln), Some(ansi)) = (crt_global::<*const i8>(ucrt, s!("__p__acmdln")), ansi) {
+        *acmdln = ansi.as_ptr();
+    }
+
+    let Some(argc) = crt_global::<i32>(ucrt, s!("__p___argc")) else {
+        return;
+    };
+    let wide_argv = crt_global::<*mut *mut u16>(ucrt, s!("__p___wargv")).filter(|x| !(**x).is_null());
+    let narrow_argv = crt_global::<*mut *mut i8>(ucrt, s!("__p___argv")).filter(|x| !(**x).is_null());
+
+    let arguments = cmdline::split_args(stripped);
+
+    let encoded_args = arguments
+        .iter()
+        .map(|x| {
+            let wide = U16CString::from_str_truncate(x);
+            paths::encode_ansi(wide.as_slice(), CP_ACP).and_then(|x| CString::new(x).ok())
+        })
+        .collect::<Option<Vec<_>>>();
+
+    // Both argv arrays share argc, so either every built array gets replaced or none do.
+    if narrow_argv.is_some() && encoded_args.is_none() {
+        return;
+    }
+
+    if let (Some(argv), Some(encoded_args)) = (narrow_argv, encoded_args) {
+        let new_argv = encoded_args
+            .into_iter()
+            .map(CString::into_raw)
+            .chain(std::iter::once(ptr::null_mut()))
+            .collect::<Vec<_>>();
+        *argv = Box::leak(new_argv.into_boxed_slice()).as_mut_ptr();
+    }
+
+    if let Some(wargv) = wide_argv {
+        let new_wargv = arguments
+            .iter()
+            .map(|x| U16CString::from_str_truncate(x).into_raw())
+            .chain(std::iter::once(ptr::null_mut()))
+            .collect::<Vec<_>>();
+        *wargv = Box::leak(new_wargv.into_boxed_slice()).as_mut_ptr();
+    }
+
+    if narrow_argv.is_some() || wide_argv.is_some() {
+        *argc = i32::try_from(arguments.len()).unwrap_or(i32::MAX);
+    }
+}
+
 pub unsafe extern "system" fn createfilew_detour(
     raw_file_name: PCWSTR,
     desired_access: u32,
@@ -165,9 +678,13 @@ pub unsafe extern "system" fn createfilew_detour(
     template_file: HANDLE,
 ) -> HANDLE {
     let path = paths::pcwstr_to_path(raw_file_name);
-    let new_path = remap_path(&path).unwrap_or_else(|| path.to_path_buf());
+    if report_hidden("createfilew_detour", &path) {
+        return INVALID_HANDLE_VALUE;
+    }
+    let write = nt::opens_for_write(desired_access, creation_disposition);
+    let new_path = remap_path_for(&path, write).unwrap_or_else(|| path.to_path_buf());
 
-    debug!("[createfilew_detour] {:?} to {:?}", path, new_path);
+    debug!("[createfilew_detour] {} to {}", path.original().display(), new_path.display());
 
     let wide_path = paths::path_to_widestring(&new_path);
 
@@ -184,6 +701,140 @@ pub unsafe extern "system" fn createfilew_detour(
     )
 }
 
+/// Re-map `path`, keeping it inside the writable layer of its mapping if it is about to be written.
+/// Files in copy-on-write mappings are copied there first.
+fn remap_path_for(path: &NormalizedPath, write: bool) -> Option<PathBuf> {
+    if !write {
+        return remap_path(path);
+    }
+
+    paths::copy_on_write(path);
+    paths::remap_path_for_write(path)
+}
+
+/// Fail a call on `path` the way a missing file would if the path is hidden.
+/// Returns whether the caller should give up and return its failure value.
+unsafe fn report_hidden(name: &str, path: &NormalizedPath) -> bool {
+    if !paths::is_hidden(path) {
+        return false;
+    }
+
+    debug!("[{name}] {path:?} is hidden");
+    SetLastError(ERROR_FILE_NOT_FOUND);
+    true
+}
+
+/// Whether the path behind an A-suffixed argument is hidden, see `report_hidden`.
+unsafe fn report_hidden_ansi(name: &str, raw_path: PCSTR) -> bool {
+    paths::pcstr_to_path(raw_path).is_some_and(|path| report_hidden(name, &path))
+}
+
+/// Drive letters and the devices behind them, for turning `\\Device\\...` names back into DOS paths.
+static DOS_DEVICES: LazyLock<Vec<(String, String)>> = LazyLock::new(|| {
+    (b'A'..=b'Z')
+        .filter_map(|letter| {
+            let drive = format!("{}:", char::from(letter));
+            let drive_wide = U16CString::from_str(&drive).ok()?;
+            let mut target = [0u16; MAX_PATH as usize];
+
+            // The first string in the result is the device the drive currently points at.
+            let len = unsafe { QueryDosDeviceW(drive_wide.as_ptr(), target.as_mut_ptr(), MAX_PATH) };
+            if len == 0 {
+                return None;
+            }
+
+            let device = U16CStr::from_slice_truncate(&target).ok()?.to_string().ok()?;
+            Some((drive, device))
+        })
+        .collect()
+});
+
+/// Work out the DOS path an `OBJECT_ATTRIBUTES` refers to, and the prefix to write it back with.
+///
+/// Names relative to `RootDirectory` are resolved against the virtual path the root handle was
+/// opened with. Returns None for names that aren't file paths and for roots that aren't tracked.
+unsafe fn read_object_path(object_attrs: *const OBJECT_ATTRIBUTES) -> Option<(NormalizedPath, nt::DosPrefix)> {
+    let object_attrs = object_attrs.as_ref()?;
+    let unicode_name = object_attrs.ObjectName.as_ref()?;
+    let units = if unicode_name.Buffer.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(unicode_name.Buffer, usize::from(unicode_name.Length / 2))
+    };
+
+    let object_name = nt::parse_object_name(units)?;
+    let prefix = object_name.dos_prefix();
+
+    let path = match (&object_name, object_attrs.RootDirectory) {
+        (nt::ObjectName::Relative(name), root) if root != 0 => {
+            let directories = VIRTUAL_DIRECTORIES.lock().unwrap_or_else(PoisonError::into_inner);
+            let root_path = &directories.get(&root)?.path;
+
+            if name.is_empty() { root_path.clone() } else { root_path.join(name) }
+        }
+        (nt::ObjectName::Dos { .. }, 0) => NormalizedPath::new(object_name.dos_path(&[])?),
+        (nt::ObjectName::Device { .. }, 0) => NormalizedPath::new(object_name.dos_path(&DOS_DEVICES)?),
+        _ => return None,
+    };
+
+    Some((path, prefix))
+}
+
+/// Call one of the native file functions with the path in `object_attrs` remapped.
+///
+/// The remapped name goes into a copy of the caller's `OBJECT_ATTRIBUTES`, so the caller's
+/// structure is never written to, and its buffer is freed once the call returns. Relative
+/// names are always rewritten to absolute ones, since the root handle may have been redirected.
+/// Hidden paths fail with `STATUS_OBJECT_NAME_NOT_FOUND` without calling the original function.
+/// When `write` is set the file is opened for writing, so copy-on-write mappings copy it first.
+/// Returns the call's status and the path the caller asked for, if it could be read.
+unsafe fn call_with_remapped_object_attributes(
+    name: &str,
+    object_attrs: *mut OBJECT_ATTRIBUTES,
+    write: bool,
+    call: impl FnOnce(*mut OBJECT_ATTRIBUTES) -> NTSTATUS,
+) -> (NTSTATUS, Option<NormalizedPath>) {
+    let Some((path, prefix)) = read_object_path(object_attrs) else {
+        return (call(object_attrs), None);
+    };
+
+    if paths::is_hidden(&path) {
+        debug!("[{name}] {path:?} is hidden");
+        return (STATUS_OBJECT_NAME_NOT_FOUND, None);
+    }
+    let relative = (*object_attrs).RootDirectory != 0;
+    let new_path = match remap_path_for(&path, write) {
+        Some(new_path) => new_path,
+        None if relative => path.to_path_buf(),
+        None => return (call(object_attrs), Some(path)),
+    };
+
+    let Some(new_path_str) = new_path.to_str() else {
+        return (call(object_attrs), Some(path));
+    };
+
+    let mut buffer = nt::serialize_object_name(prefix, new_path_str);
+    let Ok(length) = u16::try_from(buffer.len() * 2) else {
+        error!("[{name}] {} is too long for an object name", new_path.display());
+        return (call(object_attrs), Some(path));
+    };
+    buffer.push(0);
+
+    debug!("[{name}] {} to {}", path.original().display(), new_path.display());
+
+    let mut unicode_name = UNICODE_STRING {
+        Length: length,
+        MaximumLength: length.saturating_add(2),
+        Buffer: buffer.as_mut_ptr(),
+    };
+
+    let mut remapped_attrs = *object_attrs;
+    remapped_attrs.ObjectName = ptr::addr_of_mut!(unicode_name);
+    remapped_attrs.RootDirectory = 0;
+
+    (call(ptr::addr_of_mut!(remapped_attrs)), Some(path))
+}
+
 pub unsafe extern "system" fn ntcreatefile_detour(
     file_handle: *mut HANDLE,
     desired_access: u32,
@@ -197,30 +848,9 @@ pub unsafe extern "system" fn ntcreatefile_detour(
     ea_buffer: *mut c_void,
     ea_length: u32,
 ) -> NTSTATUS {
-    // The path is stored a couple layers deep in a UNICODE_STRING struct. Lets grab it.
-    let unicode_path = *(*object_attrs).ObjectName;
-    let path_len = (unicode_path.Length / 2) as usize;
-
-    // Strip the Rtl prefix from the given string. We need to reintroduce this later.
-    let og_prefix = slice::from_raw_parts(unicode_path.Buffer, 4);
-    let offset_path = unicode_path.Buffer.add(4);
-
-    // Create a raw slice and handle potential nulls safely
-    let slice = slice::from_raw_parts(offset_path, path_len - 4);
-    
-    // Find the first null terminator, if any
-    let null_pos = slice.iter().position(|&c| c == 0);
-    
-    let effective_len = null_pos.unwrap_or(path_len - 4);
-    let effective_slice = &slice[..effective_len];
-    
-    // Use from_vec instead of from_slice
-    let wide_string = WideString::from_vec(effective_slice.to_vec());
-    let original_path_result = wide_string.to_string();
-    
-    // Early return if we can't process the path
-    if original_path_result.is_err() {
-        return NtCreateFile_Detour.call(
+    let write = nt::nt_opens_for_write(desired_access, creation_disposition, create_options);
+    let (status, path) = call_with_remapped_object_attributes("ntcreatefile_detour", object_attrs, write, |object_attrs| {
+        NtCreateFile_Detour.call(
             file_handle,
             desired_access,
             object_attrs,
@@ -232,73 +862,386 @@ pub unsafe extern "system" fn ntcreatefile_detour(
             create_options,
             ea_buffer,
             ea_length
-        );
+        )
+    });
+
+    if let Some(path) = path.filter(|_| status >= 0) {
+        track_virtual_directory(*file_handle, &path, create_options);
     }
-    
-    let original_path_str = original_path_result.unwrap();
-    
-    let bad_path_prefixes = ["\\\\device", "c:\\windows"];
-    if bad_path_prefixes.iter().any(|x| {
-        let lowercase = original_path_str.to_lowercase();
-        lowercase.starts_with(&x.to_lowercase())
-    }) {
-        return NtCreateFile_Detour.call(
+
+    status
+}
+
+unsafe extern "system" fn ntopenfile_detour(
+    file_handle: *mut HANDLE,
+    desired_access: u32,
+    object_attrs: *mut OBJECT_ATTRIBUTES,
+    io_status_block: *mut IO_STATUS_BLOCK,
+    share_access: u32,
+    open_options: u32,
+) -> NTSTATUS {
+    let write = nt::nt_opens_for_write(desired_access, FILE_OPEN, open_options);
+    let (status, path) = call_with_remapped_object_attributes("ntopenfile_detour", object_attrs, write, |object_attrs| {
+        NtOpenFile_Detour.call(
             file_handle,
             desired_access,
             object_attrs,
             io_status_block,
-            allocation_size,
-            file_attrs,
             share_access,
-            creation_disposition,
-            create_options,
-            ea_buffer,
-            ea_length
+            open_options
+        )
+    });
+
+    if let Some(path) = path.filter(|_| status >= 0) {
+        track_virtual_directory(*file_handle, &path, open_options);
+    }
+
+    status
+}
+
+unsafe extern "system" fn ntqueryattributesfile_detour(
+    object_attrs: *mut OBJECT_ATTRIBUTES,
+    file_information: *mut c_void,
+) -> NTSTATUS {
+    call_with_remapped_object_attributes("ntqueryattributesfile_detour", object_attrs, false, |object_attrs| {
+        NtQueryAttributesFile_Detour.call(object_attrs, file_information)
+    }).0
+}
+
+unsafe extern "system" fn ntqueryfullattributesfile_detour(
+    object_attrs: *mut OBJECT_ATTRIBUTES,
+    file_information: *mut c_void,
+) -> NTSTATUS {
+    call_with_remapped_object_attributes("ntqueryfullattributesfile_detour", object_attrs, false, |object_attrs| {
+        NtQueryFullAttributesFile_Detour.call(object_attrs, file_information)
+    }).0
+}
+
+/// A directory listing at the native level, merged from every layer behind a virtual directory.
+struct MergedDirectoryListing {
+    class: u32,
+    records: Vec<nt::DirectoryRecord>,
+    next: usize,
+}
+
+/// A directory handle the game opened inside a mapping, or on a parent of one.
+struct VirtualDirectory {
+    /// The path the game asked for, which relative opens against the handle are resolved against.
+    path: NormalizedPath,
+    /// The real directories behind the handle when there are several to merge, otherwise empty.
+    layers: Vec<PathBuf>,
+    listing: Option<MergedDirectoryListing>,
+}
+
+/// Virtual directories opened through the native functions, keyed by the caller's handle.
+static VIRTUAL_DIRECTORIES: LazyLock<Mutex<HashMap<HANDLE, VirtualDirectory>>> = LazyLock::new(Default::default);
+
+/// How many handles `VIRTUAL_DIRECTORIES` holds, so closing other handles doesn't take its lock.
+static VIRTUAL_DIRECTORY_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// `NtQueryDirectoryFile` flags shared with `NtQueryDirectoryFileEx`.
+const SL_RESTART_SCAN: u32 = 0x1;
+const SL_RETURN_SINGLE_ENTRY: u32 = 0x2;
+const SL_INDEX_SPECIFIED: u32 = 0x4;
+
+/// `NtCreateFile` and `NtOpenFile` option for opens that must not be directories.
+const FILE_NON_DIRECTORY_FILE: u32 = 0x40;
+
+/// Remember the path behind `handle` if it may be the root of relative opens into a mapping,
+/// along with its layers if enumerating it should merge more than one directory or leave
+/// hidden entries out.
+unsafe fn track_virtual_directory(handle: HANDLE, path: &NormalizedPath, options: u32) {
+    if options & FILE_NON_DIRECTORY_FILE != 0 || !paths::overlaps_mapping(path) {
+        return;
+    }
+
+    // Directories with hidden entries go through the merged listing too, even with a single
+    // layer, so the hidden entries can be left out.
+    let layers = paths::lookup_layers(path)
+        .filter(|layers| layers.len() > 1 && paths::bypass_remapping(|| layers.iter().any(|x| x.is_dir())))
+        .or_else(|| paths::hides_inside(path).then(|| vec![path.to_path_buf()]))
+        .unwrap_or_default();
+
+    let directory = VirtualDirectory { path: path.clone(), layers, listing: None };
+    let previous = VIRTUAL_DIRECTORIES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(handle, directory);
+    if previous.is_none() {
+        VIRTUAL_DIRECTORY_COUNT.fetch_add(1, Ordering::Release);
+    }
+}
+
+/// Read every entry matching `pattern` from a single real directory.
+unsafe fn query_layer(
+    layer: &Path,
+    class: u32,
+    layout: nt::RecordLayout,
+    pattern: *mut UNICODE_STRING,
+) -> Vec<nt::DirectoryRecord> {
+    let wide_path = paths::path_to_widestring(layer);
+    let handle = CreateFileW_Detour.call(
+        wide_path.as_ptr(),
+        FILE_LIST_DIRECTORY,
+        FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+        ptr::null(),
+        OPEN_EXISTING,
+        FILE_FLAG_BACKUP_SEMANTICS,
+        0,
+    );
+    if handle == INVALID_HANDLE_VALUE {
+        return Vec::new();
+    }
+
+    let mut records = Vec::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut restart = 1;
+
+    loop {
+        let mut io_status_block: IO_STATUS_BLOCK = mem::zeroed();
+        let status = NtQueryDirectoryFile_Detour.call(
+            handle,
+            0,
+            ptr::null(),
+            ptr::null(),
+            ptr::addr_of_mut!(io_status_block),
+            buffer.as_mut_ptr().cast(),
+            u32::try_from(buffer.len()).unwrap_or(u32::MAX),
+            class,
+            0,
+            pattern,
+            restart,
         );
+        if status < 0 || status == STATUS_NO_MORE_FILES {
+            break;
+        }
+
+        let filled = buffer.get(..io_status_block.Information).unwrap_or_default();
+        match nt::parse_records(filled, layout) {
+            Some(parsed) => records.extend(parsed),
+            None => break,
+        }
+        restart = 0;
+    }
+
+    CloseHandle(handle);
+    records
+}
+
+/// Serve an `NtQueryDirectoryFile(Ex)` call on a virtual directory from its merged listing.
+///
+/// Returns None if the call should go to the original function instead: the handle has no
+/// layers to merge, the call is asynchronous or restarts at an index, or the information
+/// class isn't supported.
+#[allow(clippy::too_many_arguments)]
+unsafe fn query_virtual_directory(
+    handle: HANDLE,
+    event: HANDLE,
+    apc_routine: *const c_void,
+    io_status_block: *mut IO_STATUS_BLOCK,
+    file_information: *mut c_void,
+    length: u32,
+    class: u32,
+    flags: u32,
+    pattern: *mut UNICODE_STRING,
+) -> Option<NTSTATUS> {
+    if event != 0 || !apc_routine.is_null() || flags & SL_INDEX_SPECIFIED != 0 {
+        return None;
+    }
+    let layout = nt::RecordLayout::for_class(class)?;
+
+    let rebuild = {
+        let directories = VIRTUAL_DIRECTORIES.lock().unwrap_or_else(PoisonError::into_inner);
+        let directory = directories.get(&handle).filter(|x| !x.layers.is_empty())?;
+        let stale = directory.listing.as_ref().is_none_or(|x| x.class != class);
+
+        (stale || flags & SL_RESTART_SCAN != 0).then(|| (directory.path.clone(), directory.layers.clone()))
     };
 
-    let original_path = PathBuf::from(original_path_str);
-    let new_path = NormalizedPath::new(&original_path);
-    let new_path = remap_path(&new_path).unwrap_or_else(|| new_path.to_path_buf());
-    
-    debug!("[ntcreatefile_detour] {:?} to {:?}", original_path, new_path);
+    if (length as usize) < layout.header_len() {
+        (*io_status_block).Information = 0;
+        (*io_status_block).Anonymous.Status = STATUS_INFO_LENGTH_MISMATCH;
+        return Some(STATUS_INFO_LENGTH_MISMATCH);
+    }
 
-    // Update the Length property in the UNICODE_STRING struct with the new length of the path.
-    // (+ convert the new path back into a raw widestring and copy it into the buffer.)
-    let wide_new_path = paths::path_to_widestring(&new_path);
-    let new_path_size = (wide_new_path.len() * 2) + 8;
+    // Listing the layers opens and closes handles, so it can't happen with the table locked.
+    if let Some((path, layers)) = rebuild {
+        let listings = paths::bypass_remapping(|| {
+            layers
+                .iter()
+                .map(|layer| query_layer(layer, class, layout, pattern))
+                .collect::<Vec<_>>()
+        });
+        let mut records = paths::merge_listings(listings, nt::DirectoryRecord::name);
+        records.retain(|x| !paths::is_hidden(&path.join(x.name())));
 
-    let buffer_layout = Layout::array::<u16>(og_prefix.len() + wide_new_path.len() + 1).unwrap();
-    let buffer = alloc::alloc_zeroed(buffer_layout).cast::<u16>();
+        debug!("[query_virtual_directory] {layers:?}: {} entries", records.len());
 
-    // The length of the buffer in bytes.
-    let used_size = (og_prefix.len() + wide_new_path.len()) * 2;
-    let buffer_size = used_size + 2;
+        let mut directories = VIRTUAL_DIRECTORIES.lock().unwrap_or_else(PoisonError::into_inner);
+        directories.get_mut(&handle)?.listing = Some(MergedDirectoryListing { class, records, next: 0 });
+    }
+
+    let mut directories = VIRTUAL_DIRECTORIES.lock().unwrap_or_else(PoisonError::into_inner);
+    let listing = directories.get_mut(&handle)?.listing.as_mut()?;
 
-    ptr::copy_nonoverlapping(og_prefix.as_ptr(), buffer, og_prefix.len());
-    ptr::copy_nonoverlapping(wide_new_path.as_ptr(), buffer.add(og_prefix.len()), wide_new_path.len());
+    let remaining = listing.records.get(listing.next..).unwrap_or_default();
+    let status = if remaining.is_empty() {
+        (*io_status_block).Information = 0;
+        if listing.records.is_empty() { STATUS_NO_SUCH_FILE } else { STATUS_NO_MORE_FILES }
+    } else {
+        let buffer = slice::from_raw_parts_mut(file_information.cast::<u8>(), length as usize);
+        let (count, used) = nt::write_records(remaining, buffer, flags & SL_RETURN_SINGLE_ENTRY != 0);
+        listing.next += count;
+        (*io_status_block).Information = used;
 
-    let mut new_unicode = UNICODE_STRING {
-        Length: used_size as _,
-        MaximumLength: buffer_size as _,
-        Buffer: buffer,
+        // The buffer holds at least the fixed part of a record, so a record that didn't fit was
+        // written with a truncated name.
+        if count == 0 { STATUS_BUFFER_OVERFLOW } else { STATUS_SUCCESS }
     };
 
-    (*object_attrs).ObjectName = ptr::addr_of_mut!(new_unicode);
+    (*io_status_block).Anonymous.Status = status;
+    Some(status)
+}
+
+unsafe extern "system" fn ntquerydirectoryfile_detour(
+    file_handle: HANDLE,
+    event: HANDLE,
+    apc_routine: *const c_void,
+    apc_context: *const c_void,
+    io_status_block: *mut IO_STATUS_BLOCK,
+    file_information: *mut c_void,
+    length: u32,
+    class: u32,
+    return_single_entry: u8,
+    file_name: *mut UNICODE_STRING,
+    restart_scan: u8,
+) -> NTSTATUS {
+    let mut flags = 0;
+    if restart_scan != 0 {
+        flags |= SL_RESTART_SCAN;
+    }
+    if return_single_entry != 0 {
+        flags |= SL_RETURN_SINGLE_ENTRY;
+    }
 
-    // Call NtCreateFile now, we need to do some forgettin' before we can be done.
-    NtCreateFile_Detour.call(
+    let merged = query_virtual_directory(
         file_handle,
-        desired_access,
-        object_attrs,
+        event,
+        apc_routine,
         io_status_block,
-        allocation_size,
-        file_attrs,
-        share_access,
-        creation_disposition,
-        create_options,
-        ea_buffer,
-        ea_length
+        file_information,
+        length,
+        class,
+        flags,
+        file_name,
+    );
+
+    merged.unwrap_or_else(|| {
+        NtQueryDirectoryFile_Detour.call(
+            file_handle,
+            event,
+            apc_routine,
+            apc_context,
+            io_status_block,
+            file_information,
+            length,
+            class,
+            return_single_entry,
+            file_name,
+            restart_scan
+        )
+    })
+}
+
+unsafe extern "system" fn ntquerydirectoryfileex_detour(
+    file_handle: HANDLE,
+    event: HANDLE,
+    apc_routine: *const c_void,
+    apc_context: *const c_void,
+    io_status_block: *mut IO_STATUS_BLOCK,
+    file_information: *mut c_void,
+    length: u32,
+    class: u32,
+    query_flags: u32,
+    file_name: *mut UNICODE_STRING,
+) -> NTSTATUS {
+    let merged = query_virtual_directory(
+        file_handle,
+        event,
+        apc_routine,
+        io_status_block,
+        file_information,
+        length,
+        class,
+        query_flags,
+        file_name,
+    );
+
+    merged.unwrap_or_else(|| {
+        NtQueryDirectoryFileEx_Detour.call(
+            file_handle,
+            event,
+            apc_routine,
+            apc_context,
+            io_status_block,
+            file_information,
+            length,
+            class,
+            query_flags,
+            file_name
+        )
+    })
+}
+
+unsafe extern "system" fn ntclose_detour(handle: HANDLE) -> NTSTATUS {
+    if VIRTUAL_DIRECTORY_COUNT.load(Ordering::Acquire) != 0 {
+        let removed = VIRTUAL_DIRECTORIES
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&handle);
+        if removed.is_some() {
+            VIRTUAL_DIRECTORY_COUNT.fetch_sub(1, Ordering::Release);
+        }
+    }
+
+    NtClose_Detour.call(handle)
+}
+
+unsafe extern "system" fn createfilea_detour(
+    raw_file_name: PCSTR,
+    desired_access: u32,
+    share_mode: FILE_SHARE_MODE,
+    security_attributes: *const SECURITY_ATTRIBUTES,
+    creation_disposition: FILE_CREATION_DISPOSITION,
+    flags_attributes: FILE_FLAGS_AND_ATTRIBUTES,
+    template_file: HANDLE,
+) -> HANDLE {
+    if report_hidden_ansi("createfilea_detour", raw_file_name) {
+        return INVALID_HANDLE_VALUE;
+    }
+
+    call_with_ansi_path(
+        "createfilea_detour",
+        raw_file_name,
+        |path| remap_path_for(path, nt::opens_for_write(desired_access, creation_disposition)),
+        |raw_path| CreateFileA_Detour.call(
+            raw_path,
+            desired_access,
+            share_mode,
+            security_attributes,
+            creation_disposition,
+            flags_attributes,
+            template_file
+        ),
+        |raw_path| CreateFileW_Detour.call(
+            raw_path,
+            desired_access,
+            share_mode,
+            security_attributes,
+            creation_disposition,
+            flags_attributes,
+            template_file
+        ),
     )
 }
 
@@ -306,9 +1249,13 @@ unsafe extern "system" fn getfileattributesw_detour(
     raw_file_name: PCWSTR,
 ) -> u32 {
     let path = paths::pcwstr_to_path(raw_file_name);
+    if report_hidden("getfileattributesw_detour", &path) {
+        return INVALID_FILE_ATTRIBUTES;
+    }
+
     let new_path = remap_path(&path).unwrap_or_else(|| path.to_path_buf());
 
-    debug!("[getfileattributesw_detour] {:?} to {:?}", path, new_path);
+    debug!("[getfileattributesw_detour] {} to {}", path.original().display(), new_path.display());
 
     let wide_path = paths::path_to_widestring(&new_path);
 
@@ -329,9 +1276,13 @@ unsafe extern "system" fn getfileattributesexw_detour(
     file_information: *mut c_void,
 ) -> BOOL {
     let path = paths::pcwstr_to_path(raw_file_name);
+    if report_hidden("getfileattributesexw_detour", &path) {
+        return 0;
+    }
+
     let new_path = remap_path(&path).unwrap_or_else(|| path.to_path_buf());
 
-    debug!("[getfileattributesexw_detour] {:?} to {:?}", path, new_path);
+    debug!("[getfileattributesexw_detour] {} to {}", path.original().display(), new_path.display());
     
     let wide_path = paths::path_to_widestring(&new_path);
     
@@ -339,7 +1290,7 @@ unsafe extern "system" fn getfileattributesexw_detour(
     let attrs = GetFileAttributesW(wide_path.as_ptr());
     
     // If the path doesn't exist, handle it properly
-    if attrs == 0xFFFFFFFF {
+    if attrs == INVALID_FILE_ATTRIBUTES {
         // The error is already set by GetFileAttributesW
         return 0;
     }
@@ -352,14 +1303,574 @@ unsafe extern "system" fn getfileattributesexw_detour(
     )
 }
 
+unsafe extern "system" fn getfileattributesa_detour(
+    raw_file_name: PCSTR,
+) -> u32 {
+    if report_hidden_ansi("getfileattributesa_detour", raw_file_name) {
+        return INVALID_FILE_ATTRIBUTES;
+    }
+
+    call_with_ansi_path(
+        "getfileattributesa_detour",
+        raw_file_name,
+        remap_path,
+        |raw_path| GetFileAttributesA_Detour.call(raw_path),
+        |raw_path| GetFileAttributesW_Detour.call(raw_path),
+    )
+}
+
+unsafe extern "system" fn getfileattributesexa_detour(
+    raw_file_name: PCSTR,
+    info_level_id: GET_FILEEX_INFO_LEVELS,
+    file_information: *mut c_void,
+) -> BOOL {
+    if report_hidden_ansi("getfileattributesexa_detour", raw_file_name) {
+        return 0;
+    }
+
+    call_with_ansi_path(
+        "getfileattributesexa_detour",
+        raw_file_name,
+        remap_path,
+        |raw_path| GetFileAttributesExA_Detour.call(raw_path, info_level_id, file_information),
+        |raw_path| GetFileAttributesExW_Detour.call(raw_path, info_level_id, file_information),
+    )
+}
+
+unsafe extern "system" fn createdirectoryw_detour(
+    raw_path_name: PCWSTR,
+    security_attributes: *const SECURITY_ATTRIBUTES,
+) -> BOOL {
+    call_with_remapped_path("createdirectoryw_detour", raw_path_name, false, |raw_path| {
+        CreateDirectoryW_Detour.call(raw_path, security_attributes)
+    })
+}
+
+unsafe extern "system" fn removedirectoryw_detour(raw_path_name: PCWSTR) -> BOOL {
+    call_with_remapped_path("removedirectoryw_detour", raw_path_name, true, |raw_path| {
+        RemoveDirectoryW_Detour.call(raw_path)
+    })
+}
+
+unsafe extern "system" fn deletefilew_detour(raw_file_name: PCWSTR) -> BOOL {
+    call_with_remapped_path("deletefilew_detour", raw_file_name, true, |raw_path| DeleteFileW_Detour.call(raw_path))
+}
+
+/// Fail a removal or rename of `path` with `ERROR_ACCESS_DENIED` if it only exists in the
+/// original directory of its mapping, which mappings never change.
+/// Returns whether the caller should give up and return its failure value.
+unsafe fn report_original_only(name: &str, path: &NormalizedPath) -> bool {
+    if !paths::is_original_only(path) {
+        return false;
+    }
+
+    debug!("[{name}] {} only exists in the original directory", path.original().display());
+    SetLastError(ERROR_ACCESS_DENIED);
+    true
+}
+
+/// Call a function that creates or removes the file or directory at `raw_path` with the path
+/// remapped, so it never touches the original directory of a mapping.
+///
+/// New paths are created in the writable layer. When `remove` is set the path is removed from
+/// the layer it is found in, and paths that only exist in the original directory are refused.
+/// Hidden paths fail as missing. The original pointer is passed through when nothing is remapped.
+unsafe fn call_with_remapped_path(
+    name: &str,
+    raw_path: PCWSTR,
+    remove: bool,
+    call: impl FnOnce(PCWSTR) -> BOOL,
+) -> BOOL {
+    let path = paths::pcwstr_to_path(raw_path);
+    if report_hidden(name, &path) || (remove && report_original_only(name, &path)) {
+        return 0;
+    }
+
+    let new_path = if remove { remap_path(&path) } else { paths::remap_path_for_write(&path) };
+    let Some(new_path) = new_path else {
+        return call(raw_path);
+    };
+
+    debug!("[{name}] {} to {}", path.original().display(), new_path.display());
+
+    let wide_path = paths::path_to_widestring(&new_path);
+    call(wide_path.as_ptr())
+}
+
+/// A path argument of one of the functions taking several paths, after remapping.
+struct RemappedArgument {
+    raw: PCWSTR,
+    /// The path as given by the caller, None for null arguments.
+    path: Option<NormalizedPath>,
+    new_path: Option<PathBuf>,
+    wide_path: Option<U16CString>,
+}
+
+impl RemappedArgument {
+    /// Remap the argument at `raw`. Paths that are written to, renamed or removed are kept inside
+    /// the mapping's layers when `write` is set.
+    unsafe fn new(raw: PCWSTR, write: bool) -> Self {
+        if raw.is_null() {
+            return RemappedArgument { raw, path: None, new_path: None, wide_path: None };
+        }
+
+        let path = paths::pcwstr_to_path(raw);
+        let new_path = if write { paths::remap_path_for_write(&path) } else { remap_path(&path) };
+        let wide_path = new_path.as_deref().map(paths::path_to_widestring);
+
+        RemappedArgument { raw, path: Some(path), new_path, wide_path }
+    }
+
+    /// The pointer to pass on, which is the caller's own unless the path was remapped.
+    fn as_ptr(&self) -> PCWSTR {
+        self.wide_path.as_ref().map_or(self.raw, |x| x.as_ptr())
+    }
+
+    /// The path the call ends up operating on.
+    fn real_path(&self) -> Option<NormalizedPath> {
+        self.new_path
+            .as_ref()
+            .map(NormalizedPath::new)
+            .or_else(|| self.path.clone())
+    }
+}
+
+impl Debug for RemappedArgument {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match (&self.path, &self.new_path) {
+            (Some(path), Some(new_path)) => write!(f, "{path:?} (remapped to {new_path:?})"),
+            (Some(path), None) => write!(f, "{path:?}"),
+            (None, _) => write!(f, "null"),
+        }
+    }
+}
+
+/// Fail a call the way a missing file would if the path argument at `raw`, which the call reads
+/// from, is hidden. Null arguments are left for the call itself to reject.
+unsafe fn report_hidden_source(name: &str, raw: PCWSTR) -> bool {
+    !raw.is_null() && report_hidden(name, &paths::pcwstr_to_path(raw))
+}
+
+/// Whether remapping put two paths that shared a volume onto different volumes.
+fn remapping_crosses_volumes(a: &RemappedArgument, b: &RemappedArgument) -> bool {
+    let (Some(a_path), Some(b_path)) = (&a.path, &b.path) else {
+        return false;
+    };
+    let (Some(a_real), Some(b_real)) = (a.real_path(), b.real_path()) else {
+        return false;
+    };
+
+    a_path.same_volume(b_path) && !a_real.same_volume(&b_real)
+}
+
+unsafe extern "system" fn movefileexw_detour(
+    raw_existing_file_name: PCWSTR,
+    raw_new_file_name: PCWSTR,
+    flags: MOVE_FILE_FLAGS,
+) -> BOOL {
+    // The source is moved away from wherever it is found. Copy-on-write mappings copy a source that
+    // only exists in the original directory first, and refuse to move it out of there if that fails.
+    if !raw_existing_file_name.is_null() {
+        let path = paths::pcwstr_to_path(raw_existing_file_name);
+        if report_hidden("movefileexw_detour", &path) {
+            return 0;
+        }
+
+        paths::copy_on_write(&path);
+        if report_original_only("movefileexw_detour", &path) {
+            return 0;
+        }
+    }
+    let existing = RemappedArgument::new(raw_existing_file_name, false);
+    let new = RemappedArgument::new(raw_new_file_name, true);
+
+    // The caller expects a plain rename, which fails across volumes unless copying is allowed.
+    // Moves delayed until reboot can't be copies, so those are left alone.
+    let mut flags = flags;
+    if remapping_crosses_volumes(&existing, &new) && flags & MOVEFILE_DELAY_UNTIL_REBOOT == 0 {
+        flags |= MOVEFILE_COPY_ALLOWED;
+    }
+
+    debug!("[movefileexw_detour] {existing:?} to {new:?} with flags {flags:#x}");
+
+    MoveFileExW_Detour.call(existing.as_ptr(), new.as_ptr(), flags)
+}
+
+unsafe extern "system" fn copyfilew_detour(
+    raw_existing_file_name: PCWSTR,
+    raw_new_file_name: PCWSTR,
+    fail_if_exists: BOOL,
+) -> BOOL {
+    if report_hidden_source("copyfilew_detour", raw_existing_file_name) {
+        return 0;
+    }
+
+    let existing = RemappedArgument::new(raw_existing_file_name, false);
+    let new = RemappedArgument::new(raw_new_file_name, true);
+
+    debug!("[copyfilew_detour] {existing:?} to {new:?}");
+
+    CopyFileW_Detour.call(existing.as_ptr(), new.as_ptr(), fail_if_exists)
+}
+
+unsafe extern "system" fn replacefilew_detour(
+    raw_replaced_file_name: PCWSTR,
+    raw_replacement_file_name: PCWSTR,
+    raw_backup_file_name: PCWSTR,
+    replace_flags: REPLACE_FILE_FLAGS,
+    exclude: *const c_void,
+    reserved: *const c_void,
+) -> BOOL {
+    if report_hidden_source("replacefilew_detour", raw_replaced_file_name)
+        || report_hidden_source("replacefilew_detour", raw_replacement_file_name)
+    {
+        return 0;
+    }
+
+    // The replaced file is changed in place as far as the caller can tell, so copy-on-write mappings
+    // copy it first to keep the backup and the merged attributes those of the original.
+    if !raw_replaced_file_name.is_null() {
+        paths::copy_on_write(&paths::pcwstr_to_path(raw_replaced_file_name));
+    }
+    let replaced = RemappedArgument::new(raw_replaced_file_name, true);
+    let replacement = RemappedArgument::new(raw_replacement_file_name, true);
+    let backup = RemappedArgument::new(raw_backup_file_name, true);
+
+    debug!("[replacefilew_detour] {replaced:?} with {replacement:?}, backup {backup:?}");
+
+    // ReplaceFileW can't move the replacement onto another volume.
+    if remapping_crosses_volumes(&replaced, &replacement) {
+        return replace_across_volumes(&replaced, &replacement, &backup, replace_flags);
+    }
+
+    ReplaceFileW_Detour.call(
+        replaced.as_ptr(),
+        replacement.as_ptr(),
+        backup.as_ptr(),
+        replace_flags,
+        exclude,
+        reserved
+    )
+}
+
+/// Do what `ReplaceFileW` does for a replacement on another volume: make the backup if one was
+/// asked for, move the replacement over the replaced file and give it the replaced file's
+/// attributes and access control list.
+///
+/// Failing to carry the attributes or the access control list over fails the call unless
+/// `REPLACEFILE_IGNORE_MERGE_ERRORS` or `REPLACEFILE_IGNORE_ACL_ERRORS` says otherwise.
+unsafe fn replace_across_volumes(
+    replaced: &RemappedArgument,
+    replacement: &RemappedArgument,
+    backup: &RemappedArgument,
+    flags: REPLACE_FILE_FLAGS,
+) -> BOOL {
+    let attributes = GetFileAttributesW_Detour.call(replaced.as_ptr());
+    if attributes == INVALID_FILE_ATTRIBUTES {
+        SetLastError(ERROR_FILE_NOT_FOUND);
+        return 0;
+    }
+
+    let dacl = read_dacl(replaced.as_ptr());
+    if dacl.is_none() && flags & REPLACEFILE_IGNORE_ACL_ERRORS == 0 {
+        return 0;
+    }
+
+    if backup.path.is_some() && CopyFileW_Detour.call(replaced.as_ptr(), backup.as_ptr(), 0) == 0 {
+        return 0;
+    }
+
+    let moved = MoveFileExW_Detour.call(
+        replacement.as_ptr(),
+        replaced.as_ptr(),
+        MOVEFILE_REPLACE_EXISTING | MOVEFILE_COPY_ALLOWED,
+    );
+    if moved == 0 {
+        return 0;
+    }
+
+    if SetFileAttributesW(replaced.as_ptr(), attributes) == 0 && flags & REPLACEFILE_IGNORE_MERGE_ERRORS == 0 {
+        return 0;
+    }
+
+    if let Some(mut dacl) = dacl {
+        let applied = SetFileSecurityW(replaced.as_ptr(), DACL_SECURITY_INFORMATION, dacl.as_mut_ptr().cast());
+        if applied == 0 && flags & REPLACEFILE_IGNORE_ACL_ERRORS == 0 {
+            return 0;
+        }
+    }
+
+    TRUE
+}
+
+/// Read the security descriptor holding the access control list of the file at `path`.
+unsafe fn read_dacl(path: PCWSTR) -> Option<Vec<u8>> {
+    let mut needed = 0;
+    GetFileSecurityW(path, DACL_SECURITY_INFORMATION, ptr::null_mut(), 0, ptr::addr_of_mut!(needed));
+    if needed == 0 {
+        return None;
+    }
+
+    let mut descriptor = vec![0u8; needed as usize];
+    let read = GetFileSecurityW(
+        path,
+        DACL_SECURITY_INFORMATION,
+        descriptor.as_mut_ptr().cast(),
+        needed,
+        ptr::addr_of_mut!(needed),
+    );
+
+    (read != 0).then_some(descriptor)
+}
+
+/// `GetFinalPathNameByHandleW` flags for volume names other than drive letters.
+const VOLUME_NAME_GUID: u32 = 0x1;
+const VOLUME_NAME_NT: u32 = 0x2;
+const VOLUME_NAME_NONE: u32 = 0x4;
+
+/// Fill a buffer through one of the functions that report the size they need when it's too small.
+/// Returns None if the call fails.
+fn call_with_wide_buffer(mut call: impl FnMut(*mut u16, u32) -> u32) -> Option<Vec<u16>> {
+    let mut buffer = vec![0u16; MAX_PATH as usize];
+    loop {
+        let capacity = u32::try_from(buffer.len()).ok()?;
+        let len = call(buffer.as_mut_ptr(), capacity);
+        if len == 0 {
+            return None;
+        }
+
+        if len < capacity {
+            buffer.truncate(len as usize);
+            return Some(buffer);
+        }
+        buffer.resize(len as usize, 0);
+    }
+}
+
+/// Copy a path into a caller's buffer the way the path functions do.
+/// Returns the length written without the terminator, or the size needed including it.
+unsafe fn write_wide_result(result: &[u16], buffer: PWSTR, capacity: u32) -> u32 {
+    let len = u32::try_from(result.len()).unwrap_or(u32::MAX - 1);
+    if buffer.is_null() || len >= capacity {
+        return len + 1;
+    }
+
+    ptr::copy_nonoverlapping(result.as_ptr(), buffer, result.len());
+    *buffer.add(result.len()) = 0;
+    len
+}
+
+/// Map a path reported by Windows back to the virtual path the game expects, keeping any `\\?\` prefix.
+/// Returns None if the path isn't inside a redirect target.
+fn reverse_remap_reported(reported: &[u16]) -> Option<Vec<u16>> {
+    let (prefix, path) = match nt::parse_object_name(reported) {
+        Some(nt::ObjectName::Dos { prefix, path }) => (Some(prefix), path),
+        _ => (None, String::from_utf16(reported).ok()?),
+    };
+
+    let virtual_path = paths::reverse_remap_path(&NormalizedPath::new(&path))?;
+    let virtual_path = virtual_path.to_str()?;
+
+    debug!("[reverse_remap_reported] {path:?} to {virtual_path:?}");
+
+    match prefix {
+        Some(prefix) => Some(nt::serialize_object_name(prefix, virtual_path)),
+        None => Some(virtual_path.encode_utf16().collect()),
+    }
+}
+
+unsafe extern "system" fn getfinalpathnamebyhandlew_detour(
+    file: HANDLE,
+    file_path: PWSTR,
+    capacity: u32,
+    flags: FILE_NAME,
+) -> u32 {
+    // Only drive letter paths can be mapped back, volume GUIDs and NT names are passed through.
+    if flags & (VOLUME_NAME_GUID | VOLUME_NAME_NT | VOLUME_NAME_NONE) != 0 {
+        return GetFinalPathNameByHandleW_Detour.call(file, file_path, capacity, flags);
+    }
+
+    let Some(real_path) = call_with_wide_buffer(|buffer, size| {
+        GetFinalPathNameByHandleW_Detour.call(file, buffer, size, flags)
+    }) else {
+        return 0;
+    };
+
+    let path = reverse_remap_reported(&real_path).unwrap_or(real_path);
+    write_wide_result(&path, file_path, capacity)
+}
+
+unsafe extern "system" fn getfullpathnamew_detour(
+    file_name: PCWSTR,
+    capacity: u32,
+    buffer: PWSTR,
+    file_part: *mut PWSTR,
+) -> u32 {
+    let Some(full_path) = call_with_wide_buffer(|full_buffer, size| {
+        GetFullPathNameW_Detour.call(file_name, size, full_buffer, ptr::null_mut())
+    }) else {
+        return 0;
+    };
+
+    // Only relative paths are resolved against the real current directory, which may be a redirect
+    // target. Absolute paths come back as given, real ones included, and stay that way.
+    let relative = !paths::pcwstr_to_path(file_name).original().is_absolute();
+    let path = if relative { reverse_remap_reported(&full_path).unwrap_or(full_path) } else { full_path };
+    let len = write_wide_result(&path, buffer, capacity);
+
+    // The file part points into the caller's buffer, or is null for paths ending in a separator.
+    if !file_part.is_null() && len < capacity {
+        *file_part = match path.iter().rposition(|x| *x == u16::from(b'\\')) {
+            Some(separator) if separator + 1 < path.len() => buffer.add(separator + 1),
+            _ => ptr::null_mut(),
+        };
+    }
+
+    len
+}
+
+unsafe extern "system" fn setcurrentdirectoryw_detour(raw_path_name: PCWSTR) -> BOOL {
+    if raw_path_name.is_null() {
+        return SetCurrentDirectoryW_Detour.call(raw_path_name);
+    }
+
+    let path = paths::pcwstr_to_path(raw_path_name);
+    let result = match remap_path(&path) {
+        Some(new_path) => {
+            debug!("[setcurrentdirectoryw_detour] {} to {}", path.original().display(), new_path.display());
+
+            let wide_path = paths::path_to_widestring(&new_path);
+            SetCurrentDirectoryW_Detour.call(wide_path.as_ptr())
+        }
+        None => SetCurrentDirectoryW_Detour.call(raw_path_name),
+    };
+    if result == 0 {
+        return result;
+    }
+
+    // The game sees the virtual path while the real directory is a redirect target, or while
+    // it's an original directory covered by a mapping so relative paths still get remapped.
+    let virtual_cwd = call_with_wide_buffer(|buffer, size| GetCurrentDirectoryW_Detour.call(size, buffer))
+        .and_then(|x| String::from_utf16(&x).ok())
+        .and_then(|real_cwd| {
+            let real_cwd = NormalizedPath::new(real_cwd);
+            match paths::reverse_remap_path(&real_cwd) {
+                Some(virtual_cwd) => Some(NormalizedPath::new(virtual_cwd)),
+                None => paths::overlaps_mapping(&real_cwd).then_some(real_cwd),
+            }
+        });
+
+    debug!("[setcurrentdirectoryw_detour] Virtual current directory: {virtual_cwd:?}");
+    paths::set_virtual_cwd(virtual_cwd);
+
+    result
+}
+
+unsafe extern "system" fn getcurrentdirectoryw_detour(capacity: u32, buffer: PWSTR) -> u32 {
+    let Some(virtual_cwd) = paths::virtual_cwd() else {
+        return GetCurrentDirectoryW_Detour.call(capacity, buffer);
+    };
+
+    let wide_path = paths::path_to_widestring(virtual_cwd.original());
+    write_wide_result(wide_path.as_slice(), buffer, capacity)
+}
+
+/// A directory listing merged from every layer behind a virtual directory.
+struct MergedFind {
+    entries: Vec<WIN32_FIND_DATAW>,
+    next: usize,
+}
+
+/// Merged listings handed out by the `FindFirstFile` detours, keyed by their synthetic handle.
+/// The handle is the address of the boxed listing, so it never collides with a real handle.
+static MERGED_FINDS: LazyLock<Mutex<HashMap<FindFileHandle, Box<MergedFind>>>> = LazyLock::new(Default::default);
+
+/// Enumerate `search` in every layer behind its directory and merge the results.
+///
+/// Hidden entries are left out, and directories with hidden entries are searched this way even
+/// without layers to merge. Returns None if the searched directory is not a virtual directory,
+/// in which case the caller should fall back to a regular search.
+unsafe fn merged_find(
+    search: &NormalizedPath,
+    find_file_data: *mut WIN32_FIND_DATAW,
+    find_first: impl Fn(PCWSTR, *mut WIN32_FIND_DATAW) -> FindFileHandle,
+) -> Option<FindFileHandle> {
+    let (directory, pattern) = paths::split_search_pattern(search.original())?;
+    let directory = NormalizedPath::new(directory);
+    if report_hidden("merged_find", &directory) {
+        return Some(INVALID_HANDLE_VALUE);
+    }
+
+    let layers = paths::lookup_layers(&directory)
+        .or_else(|| paths::hides_inside(&directory).then(|| vec![directory.to_path_buf()]))?;
+
+    let listings = paths::bypass_remapping(|| {
+        layers
+            .iter()
+            .map(|layer| {
+                let wide_path = paths::path_to_widestring(&layer.join(pattern));
+                let mut entries = Vec::new();
+                let mut data: WIN32_FIND_DATAW = mem::zeroed();
+
+                let handle = find_first(wide_path.as_ptr(), ptr::addr_of_mut!(data));
+                if handle == INVALID_HANDLE_VALUE {
+                    return entries;
+                }
+
+                entries.push(data);
+                while FindNextFileW_Detour.call(handle, ptr::addr_of_mut!(data)) != 0 {
+                    entries.push(data);
+                }
+
+                FindClose_Detour.call(handle);
+                entries
+            })
+            .collect::<Vec<_>>()
+    });
+
+    let file_name = |data: &WIN32_FIND_DATAW| {
+        U16CStr::from_slice_truncate(&data.cFileName)
+            .map(U16CStr::to_string_lossy)
+            .unwrap_or_default()
+    };
+    let mut entries = paths::merge_listings(listings, file_name);
+    entries.retain(|x| !paths::is_hidden(&directory.join(file_name(x))));
+
+    debug!("[merged_find] {search:?} across {layers:?}: {} entries", entries.len());
+
+    let Some(first) = entries.first() else {
+        let missing = paths::bypass_remapping(|| !layers.iter().any(|x| x.is_dir()));
+        SetLastError(if missing { ERROR_PATH_NOT_FOUND } else { ERROR_FILE_NOT_FOUND });
+        return Some(INVALID_HANDLE_VALUE);
+    };
+    *find_file_data = *first;
+
+    let find = Box::new(MergedFind { entries, next: 1 });
+    let handle = ptr::addr_of!(*find) as FindFileHandle;
+    MERGED_FINDS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(handle, find);
+
+    Some(handle)
+}
+
 unsafe extern "system" fn findfirstfilew_detour(
     raw_file_name: PCWSTR,
     find_file_data: *mut WIN32_FIND_DATAW,
 ) -> FindFileHandle {
     let path = paths::pcwstr_to_path(raw_file_name);
+
+    let merged = merged_find(&path, find_file_data, |search, data| {
+        FindFirstFileW_Detour.call(search, data)
+    });
+    if let Some(handle) = merged {
+        return handle;
+    }
+
     let new_path = remap_path(&path).unwrap_or_else(|| path.to_path_buf());
 
-    debug!("[findfirstfilew_detour] {:?} to {:?}", path, new_path);
+    debug!("[findfirstfilew_detour] {} to {}", path.original().display(), new_path.display());
 
     let wide_path = paths::path_to_widestring(&new_path);
 
@@ -384,9 +1895,25 @@ unsafe extern "system" fn findfirstfileexw_detour(
     additional_flags: FIND_FIRST_EX_FLAGS
 ) -> FindFileHandle {
     let path = paths::pcwstr_to_path(raw_file_name);
+
+    // Both FindExInfoStandard and FindExInfoBasic fill in a WIN32_FIND_DATAW.
+    let merged = merged_find(&path, find_file_data.cast(), |search, data| {
+        FindFirstFileExW_Detour.call(
+            search,
+            info_level_id,
+            data.cast(),
+            search_op,
+            search_filter,
+            additional_flags
+        )
+    });
+    if let Some(handle) = merged {
+        return handle;
+    }
+
     let new_path = remap_path(&path).unwrap_or_else(|| path.to_path_buf());
 
-    debug!("[findfirstfileexw_detour] {:?} to {:?}", path, new_path);
+    debug!("[findfirstfileexw_detour] {} to {}", path.original().display(), new_path.display());
 
     let wide_path = paths::path_to_widestring(&new_path);
 
@@ -402,11 +1929,238 @@ unsafe extern "system" fn findfirstfileexw_detour(
     )
 }
 
+/// The result of advancing a find handle that may belong to a merged listing.
+#[allow(clippy::large_enum_variant)]
+enum MergedNext {
+    /// The handle is a regular find handle.
+    NotMerged,
+    Entry(WIN32_FIND_DATAW),
+    Exhausted,
+}
+
+/// Advance the merged listing behind `find_file`.
+fn next_merged_entry(find_file: FindFileHandle) -> MergedNext {
+    let mut finds = MERGED_FINDS.lock().unwrap_or_else(PoisonError::into_inner);
+    let Some(find) = finds.get_mut(&find_file) else {
+        return MergedNext::NotMerged;
+    };
+
+    let Some(entry) = find.entries.get(find.next) else {
+        return MergedNext::Exhausted;
+    };
+
+    find.next += 1;
+    MergedNext::Entry(*entry)
+}
+
+unsafe extern "system" fn findnextfilew_detour(
+    find_file: FindFileHandle,
+    find_file_data: *mut WIN32_FIND_DATAW,
+) -> BOOL {
+    match next_merged_entry(find_file) {
+        MergedNext::NotMerged => FindNextFileW_Detour.call(find_file, find_file_data),
+        MergedNext::Exhausted => {
+            SetLastError(ERROR_NO_MORE_FILES);
+            0
+        }
+        MergedNext::Entry(entry) => {
+            *find_file_data = entry;
+            TRUE
+        }
+    }
+}
+
+unsafe extern "system" fn findfirstfilea_detour(
+    raw_file_name: PCSTR,
+    find_file_data: *mut WIN32_FIND_DATAA,
+) -> FindFileHandle {
+    // Searches are done through the wide functions and converted, merged listings only hold wide entries.
+    let find_first_wide = |search: PCWSTR| {
+        let mut data: WIN32_FIND_DATAW = mem::zeroed();
+        let handle = FindFirstFileW_Detour.call(search, ptr::addr_of_mut!(data));
+        if handle != INVALID_HANDLE_VALUE {
+            *find_file_data = paths::find_data_to_ansi(&data, CP_ACP);
+        }
+        handle
+    };
+
+    if let Some(path) = paths::pcstr_to_path(raw_file_name) {
+        let mut data: WIN32_FIND_DATAW = mem::zeroed();
+        let merged = merged_find(&path, ptr::addr_of_mut!(data), |search, data| {
+            FindFirstFileW_Detour.call(search, data)
+        });
+        if let Some(handle) = merged {
+            if handle != INVALID_HANDLE_VALUE {
+                *find_file_data = paths::find_data_to_ansi(&data, CP_ACP);
+            }
+            return handle;
+        }
+    }
+
+    call_with_ansi_path(
+        "findfirstfilea_detour",
+        raw_file_name,
+        remap_path,
+        |raw_path| FindFirstFileA_Detour.call(raw_path, find_file_data),
+        find_first_wide,
+    )
+}
+
+unsafe extern "system" fn findnextfilea_detour(
+    find_file: FindFileHandle,
+    find_file_data: *mut WIN32_FIND_DATAA,
+) -> BOOL {
+    match next_merged_entry(find_file) {
+        MergedNext::NotMerged => FindNextFileA_Detour.call(find_file, find_file_data),
+        MergedNext::Exhausted => {
+            SetLastError(ERROR_NO_MORE_FILES);
+            0
+        }
+        MergedNext::Entry(entry) => {
+            *find_file_data = paths::find_data_to_ansi(&entry, CP_ACP);
+            TRUE
+        }
+    }
+}
+
+unsafe extern "system" fn findclose_detour(find_file: FindFileHandle) -> BOOL {
+    let merged = MERGED_FINDS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(&find_file);
+
+    if merged.is_some() {
+        return TRUE;
+    }
+
+    FindClose_Detour.call(find_file)
+}
+
+/// Look up an export of ntdll.
+unsafe fn ntdll_export(name: PCSTR) -> Result<unsafe extern "system" fn() -> isize, Box<dyn Error>> {
+    let ntdll = GetModuleHandleW(w!("ntdll.dll"));
+    if ntdll == 0 {
+        return Err("ntdll.dll is not loaded".into());
+    }
+
+    GetProcAddress(ntdll, name).ok_or_else(|| {
+        let name = CStr::from_ptr(name.cast()).to_string_lossy();
+        format!("ntdll.dll does not export {name}").into()
+    })
+}
+
+/// `_wfopen` from ucrtbase, for remapped paths the active code page can't represent.
+static WFOPEN: OnceLock<unsafe extern "C" fn(PCWSTR, PCWSTR) -> *mut c_void> = OnceLock::new();
+
+/// Initialize the `fopen` detour if the game uses ucrtbase.
+/// Returns whether the detour was initialized.
+unsafe fn initialize_fopen_detour() -> Result<bool, Box<dyn Error>> {
+    let ucrt = GetModuleHandleW(w!("ucrtbase.dll"));
+    if ucrt == 0 {
+        return Ok(false);
+    }
+
+    let (Some(fopen), Some(wfopen)) = (GetProcAddress(ucrt, s!("fopen")), GetProcAddress(ucrt, s!("_wfopen"))) else {
+        return Ok(false);
+    };
+    let fopen: unsafe extern "C" fn(PCSTR, PCSTR) -> *mut c_void = mem::transmute(fopen);
+    let wfopen: unsafe extern "C" fn(PCWSTR, PCWSTR) -> *mut c_void = mem::transmute(wfopen);
+    let _ = WFOPEN.set(wfopen);
+
+    fopen_Detour.initialize(fopen, |a, b| unsafe {
+        fopen_detour(a, b)
+    })?;
+
+    Ok(true)
+}
+
+unsafe extern "C" fn fopen_detour(raw_file_name: PCSTR, mode: PCSTR) -> *mut c_void {
+    if mode.is_null() {
+        return fopen_Detour.call(raw_file_name, mode);
+    }
+
+    // Any mode that writes, appends or updates has to stay inside the writable layer.
+    let write = CStr::from_ptr(mode.cast()).to_bytes().iter().any(|x| matches!(x, b'w' | b'a' | b'+'));
+
+    call_with_ansi_path(
+        "fopen_detour",
+        raw_file_name,
+        |path| remap_path_for(path, write),
+        |raw_path| fopen_Detour.call(raw_path, mode),
+        |raw_path| {
+            // Modes are plain ASCII, so widening each byte is enough.
+            let wide_mode = CStr::from_ptr(mode.cast())
+                .to_bytes()
+                .iter()
+                .map(|x| u16::from(*x))
+                .collect::<Vec<_>>();
+            let wide_mode = U16CString::from_vec_truncate(wide_mode);
+
+            match WFOPEN.get() {
+                Some(wfopen) => wfopen(raw_path, wide_mode.as_ptr()),
+                None => ptr::null_mut(),
+            }
+        },
+    )
+}
+
+/// The directory containing the game executable, the first place the loader searches.
+static APP_DIR: LazyLock<Option<PathBuf>> = LazyLock::new(|| {
+    std::env::current_exe()
+        .ok()
+        .and_then(|x| x.parent().map(Path::to_path_buf))
+});
+
+/// Re-map a path handed to one of the `LoadLibrary` functions.
+///
+/// Bare module names are left to the loader's search order, which already sees remapped
+/// `AddDllDirectory` entries. Relative paths with a directory component are resolved against
+/// the application directory first, so `Mods\Foo\dlls\main.dll` still reaches the redirected
+/// directory. The load flags are passed through untouched.
+fn remap_library_path(path: &NormalizedPath) -> Option<PathBuf> {
+    let original = path.original();
+    if original.has_root() {
+        return remap_path(path);
+    }
+
+    if original.components().count() < 2 {
+        return None;
+    }
+
+    let app_dir = APP_DIR.as_ref()?;
+    remap_path(&NormalizedPath::new(app_dir.join(original)))
+}
+
+/// Call an A-suffixed function with its path argument remapped by `remap`.
+///
+/// The original pointer is passed through when nothing is remapped. A remapped path that the
+/// active code page can't represent goes to the wide variant through `call_w` instead.
+unsafe fn call_with_ansi_path<T>(
+    name: &str,
+    raw_path: PCSTR,
+    remap: impl FnOnce(&NormalizedPath) -> Option<PathBuf>,
+    call_a: impl FnOnce(PCSTR) -> T,
+    call_w: impl FnOnce(PCWSTR) -> T,
+) -> T {
+    let Some(path) = paths::pcstr_to_path(raw_path) else {
+        return call_a(raw_path);
+    };
+    let Some(new_path) = remap(&path) else {
+        return call_a(raw_path);
+    };
+
+    debug!("[{name}] {} to {}", path.original().display(), new_path.display());
+
+    match paths::path_to_ansi(&new_path, CP_ACP) {
+        Some(ansi_path) => call_a(ansi_path.as_ptr().cast()),
+        None => call_w(paths::path_to_widestring(&new_path).as_ptr()),
+    }
+}
 
 unsafe extern "system" fn loadlibraryw_detour(lpfilename: PCWSTR) -> HMODULE {
     let path = paths::pcwstr_to_path(lpfilename);
-    let new_path = remap_path(&path).unwrap_or_else(|| path.to_path_buf());
-    debug!("[loadlibraryw_detour] {:?} to {:?}", path, new_path);
+    let new_path = remap_library_path(&path).unwrap_or_else(|| path.to_path_buf());
+    debug!("[loadlibraryw_detour] {} to {}", path.original().display(), new_path.display());
 
     let wide_path = paths::path_to_widestring(&new_path);
 
@@ -415,11 +2169,59 @@ unsafe extern "system" fn loadlibraryw_detour(lpfilename: PCWSTR) -> HMODULE {
     LoadLibraryW_Detour.call(raw_path)
 }
 
+unsafe extern "system" fn loadlibraryexw_detour(
+    lpfilename: PCWSTR,
+    file: HANDLE,
+    flags: LOAD_LIBRARY_FLAGS,
+) -> HMODULE {
+    let path = paths::pcwstr_to_path(lpfilename);
+    let new_path = remap_library_path(&path).unwrap_or_else(|| path.to_path_buf());
+    debug!(
+        "[loadlibraryexw_detour] {} to {} with flags {flags:#x}",
+        path.original().display(),
+        new_path.display()
+    );
+
+    let wide_path = paths::path_to_widestring(&new_path);
+
+    let raw_path = if path.to_path_buf() == new_path {
+        lpfilename
+    } else {
+        wide_path.as_ptr()
+    };
+
+    LoadLibraryExW_Detour.call(raw_path, file, flags)
+}
+
+unsafe extern "system" fn loadlibrarya_detour(lpfilename: PCSTR) -> HMODULE {
+    call_with_ansi_path(
+        "loadlibrarya_detour",
+        lpfilename,
+        remap_library_path,
+        |raw_path| LoadLibraryA_Detour.call(raw_path),
+        |raw_path| LoadLibraryW_Detour.call(raw_path),
+    )
+}
+
+unsafe extern "system" fn loadlibraryexa_detour(
+    lpfilename: PCSTR,
+    file: HANDLE,
+    flags: LOAD_LIBRARY_FLAGS,
+) -> HMODULE {
+    call_with_ansi_path(
+        "loadlibraryexa_detour",
+        lpfilename,
+        remap_library_path,
+        |raw_path| LoadLibraryExA_Detour.call(raw_path, file, flags),
+        |raw_path| LoadLibraryExW_Detour.call(raw_path, file, flags),
+    )
+}
+
 unsafe extern "system" fn adddlldirectory_detour(lppathnamestr: PCWSTR) -> *mut c_void {
     let path = paths::pcwstr_to_path(lppathnamestr);
     let new_path = remap_path(&path).unwrap_or_else(|| path.to_path_buf());
     
-    debug!("[adddlldirectory_detour] {:?} to {:?}", path, new_path);
+    debug!("[adddlldirectory_detour] {} to {}", path.original().display(), new_path.display());
 
     let wide_path = paths::path_to_widestring(&new_path);
     let raw_path = wide_path.as_ptr();
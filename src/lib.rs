@@ -1,3 +1,4 @@
+#![recursion_limit = "512"]
 #![allow(unused, clippy::undocumented_unsafe_blocks)]
 #![warn(
     clippy::pedantic,
@@ -12,12 +13,15 @@ use std::ffi::c_void;
 use std::fs::{canonicalize, File};
 use std::ops::Index;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 use chrono::Local;
+use config::{Config, ErrorPolicy, MappingConfig};
+use error::ShimError;
 use log::{debug, error, LevelFilter};
 use getargs::{Arg, Opt, Options};
-use once_cell::sync::{Lazy, OnceCell};
-use paths::{NormalizedPath, PathRegistry, PATH_REGISTRY};
+use paths::{NormalizedPath, PathRegistry, VirtualFileStore, PATH_REGISTRY};
+use ue4ss::SettingOverride;
 use widestring::U16CString;
 use windows_sys::w;
 use windows_sys::Win32::Foundation::{BOOL, HWND, TRUE};
@@ -26,30 +30,25 @@ use windows_sys::Win32::System::Diagnostics::Debug::DebugActiveProcess;
 use windows_sys::Win32::System::LibraryLoader::LoadLibraryW;
 use windows_sys::Win32::System::SystemServices::DLL_PROCESS_ATTACH;
 use windows_sys::Win32::System::Threading::{GetCurrentProcess, GetProcessId};
-use windows_sys::Win32::UI::WindowsAndMessaging::{MESSAGEBOX_STYLE, MessageBoxW};
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    MESSAGEBOX_STYLE, MB_ICONERROR, MB_ICONWARNING, MB_OK, MessageBoxW,
+};
 
+mod cmdline;
+mod config;
+mod error;
+mod game_root;
 mod hooks;
+mod nt;
 mod paths;
+#[cfg(test)]
+mod test_utils;
+mod ue4ss;
 mod utils;
 
-static GAME_ROOT: Lazy<PathBuf> = Lazy::new(|| {
-    let current_exe = env::current_exe().unwrap();
-    current_exe
-        .ancestors()
-        .nth(3)
-        .unwrap_or_else(|| 
-            panic!("The executable at {current_exe:?} is not contained within a valid UE directory structure."))
-        .to_path_buf()
-});
-
-static EXE_DIR: Lazy<PathBuf> = Lazy::new(|| {
-    env::current_exe()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .to_path_buf()
-});
-
+/// # Safety
+///
+/// Only meant to be called by the Windows loader.
 #[no_mangle]
 #[allow(non_snake_case)]
 pub unsafe extern "system" fn DllMain(
@@ -73,24 +72,29 @@ unsafe fn shim_init() {
     std::panic::set_hook(Box::new(|x| unsafe {
         let message = format!("unreal-shimloader has crashed: \n\n{x}");
         error!("{message}");
-
-        let message = U16CString::from_str(message);
-        MessageBoxW(
-            0,
-            message.unwrap().as_ptr(),
-            w!("unreal-shimloader"),
-            0
-        );
+        show_message(&message, MB_OK);
     }));
 
-    let current_exe = env::current_exe()
-        .expect("Failed to get the path of the currently running executable.");
-    let exe_dir = current_exe.parent().unwrap();
- 
-    let mut target = Box::new(File::create(exe_dir.join("shimloader-log.txt")).expect("Failed to create log file."));
+    // Until the configuration has been read, failures are handled with the default policy.
+    let mut on_error = ErrorPolicy::default();
+    if let Err(e) = try_init(&mut on_error) {
+        handle_init_error(&e, on_error);
+    }
+}
+
+#[allow(clippy::too_many_lines)]
+unsafe fn try_init(on_error: &mut ErrorPolicy) -> Result<(), ShimError> {
+    let current_exe = env::current_exe().map_err(ShimError::CurrentExe)?;
+    let exe_dir = current_exe
+        .parent()
+        .ok_or_else(|| ShimError::GameLayout(current_exe.clone()))?;
+
+    // The logger comes first so configuration errors reach the log even under the silent policy.
+    // It starts at the default level and moves to the configured one once that is known.
+    let mut target = Box::new(File::create(exe_dir.join("shimloader-log.txt")).map_err(ShimError::LogFile)?);
     env_logger::Builder::new()
         .target(env_logger::Target::Pipe(target))
-        .filter(None, LevelFilter::Debug)
+        .filter(None, LevelFilter::Trace)
         .format(|buf, record| {
             writeln!(
                 buf,
@@ -103,49 +107,48 @@ unsafe fn shim_init() {
             )
         })
         .init();
+    log::set_max_level(Config::default().log_level);
+
+    let config = load_config(exe_dir, on_error)?;
+    log::set_max_level(config.log_level);
 
     debug!("unreal_shimloader -- start");
-    debug!("current directory: {exe_dir:?}");
-    debug!("current executable: {current_exe:?}");
+    debug!("current directory: {}", exe_dir.display());
+    debug!("current executable: {}", current_exe.display());
     debug!("args: {:?}", env::args().collect::<Vec<_>>());
+    debug!("config: {config:?}");
 
     // Ensure that UE4SS is not installed via xinput1_3.dll
     let xinput_path = exe_dir.join("xinput1_3.dll");
-    assert!(
-        !xinput_path.exists(), 
-        "Shimloader is not compatible with the xinput1_3.dll UE4SS binary.\n
-        1. Remove the file at {xinput_path:?} \n
-        2. Ensure that ue4ss.dll exists within {exe_dir:?} \n
-        3. Run the game again.",
-    );
-
-    let args = env::args().skip(1).collect::<Vec<_>>();
-    let mut opts = Options::new(args.iter().map(String::as_str));
-
-    let mut lua_dir: Option<PathBuf> = None;
-    let mut pak_dir: Option<PathBuf> = None;
-    let mut cfg_dir: Option<PathBuf> = None;
+    if xinput_path.exists() {
+        return Err(ShimError::XInputConflict {
+            xinput_path,
+            exe_dir: exe_dir.to_path_buf(),
+        });
+    }
 
-    while let Some(opt) = opts.next_arg().expect("Failed to parse arguments") {
-        match opt {
-            Arg::Long("mod-dir") => lua_dir = Some(PathBuf::from(opts.value().expect("`--mod-dir` argument has no value."))),
-            Arg::Long("pak-dir") => pak_dir = Some(PathBuf::from(opts.value().expect("`--pak-dir` argument has no value."))),
-            Arg::Long("cfg-dir") => cfg_dir = Some(PathBuf::from(opts.value().expect("`--cfg-dir` argument has no value."))),
-            _ => (),
+    if config.hooks.command_line {
+        if let Err(e) = hooks::hide_shim_args() {
+            error!("Failed to hide shimloader arguments from the game. {e}");
         }
     }
 
-    // If no args are specified then we start the game with ue4ss and mods disabled.
-    let run_vanilla = ![&lua_dir, &pak_dir, &cfg_dir].iter().any(|x| x.is_some());
+    // If no mappings are specified then we start the game with ue4ss and mods disabled.
+    let run_vanilla = !config.has_mappings();
     if run_vanilla {
-        return;
+        return Ok(());
     }
 
-    let toplevel_dir = current_exe
-        .ancestors()
-        .nth(3)
-        .unwrap_or_else(|| 
-            panic!("The executable at {current_exe:?} is not contained within a valid UE directory structure."));
+    let game_root = game_root::find_game_root(&current_exe)
+        .ok_or_else(|| ShimError::GameLayout(current_exe.clone()))?;
+    debug!("game root: {} (matched {})", game_root.path.display(), game_root.rule);
+    let toplevel_dir = game_root.path.as_path();
+
+    // Check for the DLLs up front so a missing one doesn't leave the game half hooked.
+    let dll_paths = config.load.iter().map(|x| exe_dir.join(x)).collect::<Vec<_>>();
+    if let Some(missing) = dll_paths.iter().find(|x| !x.is_file()) {
+        return Err(ShimError::MissingDll(missing.clone()));
+    }
 
     // Validation to ensure that the Content/Paks/LogicMods directory exists in the game directory.
     // This is really janky to do in DllMain. Oh well!
@@ -166,50 +169,298 @@ unsafe fn shim_init() {
     }
 
     // Create the ue4ss_mods and bp_mods directories if they don't already exist.
-    let ue4ss_mods = paths::NormalizedPath::new(&lua_dir.unwrap());
-    let bp_mods = paths::NormalizedPath::new(&pak_dir.unwrap());
-    let config_dir = paths::NormalizedPath::new(&cfg_dir.unwrap());
+    let ue4ss_mods = config.mod_dirs.iter().map(paths::NormalizedPath::new).collect::<Vec<_>>();
+    let bp_mods = config.pak_dirs.iter().map(paths::NormalizedPath::new).collect::<Vec<_>>();
+    let config_dirs = config.cfg_dirs.iter().map(paths::NormalizedPath::new).collect::<Vec<_>>();
 
-    for dir in [ue4ss_mods.as_ref(), bp_mods.as_ref(), config_dir.as_ref()] {
+    for dir in ue4ss_mods.iter().chain(&bp_mods).chain(&config_dirs) {
         let _ = fs::create_dir_all(dir);
     }
     
     // Build the path registry with all virtual directory mappings.
     let mut registry = PathRegistry::new();
+    let mut virtual_files = VirtualFileStore::for_game(&current_exe);
 
-    // Lua mods: GAME/Binaries/Win64/Mods/ -> user's mod directory
-    registry.register(EXE_DIR.join("Mods"), ue4ss_mods.to_path_buf());
+    // Lua mods: GAME/Binaries/Win64/Mods/ -> user's mod directories
+    let mods_dir = exe_dir.join("Mods");
+    if config.generate_mod_list && !config.mod_dirs.is_empty() {
+        register_mod_list(&mut registry, &mut virtual_files, &mods_dir, &config.mod_dirs);
+    }
+    registry.register_overlay(mods_dir.as_path(), ue4ss_mods, 0);
     
-    // Blueprint mods: GAME/Content/Paks/LogicMods/ -> user's pak directory
-    // NormalizedPath automatically cleans .. components
-    let bp_source = EXE_DIR
-        .join("..")
-        .join("..")
+    // Blueprint mods: GAME/Content/Paks/LogicMods/ -> user's pak directories
+    let bp_source = toplevel_dir
         .join("Content")
         .join("Paks")
         .join("LogicMods");
-    registry.register(bp_source, bp_mods.to_path_buf());
+    registry.register_overlay(bp_source, bp_mods, 0);
     
-    // Config: GAME/Config/ -> user's config directory
-    let config_source = EXE_DIR
-        .join("..")
-        .join("..")
-        .join("Config");
-    registry.register(config_source, config_dir.to_path_buf());
+    // Config: GAME/Config/ -> user's config directories
+    let config_source = toplevel_dir.join("Config");
+    registry.register_overlay(config_source, config_dirs, 0);
+
+    // Generic mappings from the configuration file, `--map` and `--hide`.
+    for mapping in config.mappings {
+        let source = toplevel_dir.join(mapping.source);
+        if mapping.hide {
+            registry.hide(source);
+        } else if mapping.copy_on_write {
+            registry.register_copy_on_write(source, mapping.targets, mapping.writable);
+        } else {
+            registry.register_overlay(source, mapping.targets, mapping.writable);
+        }
+    }
+
+    if !config.ue4ss_settings.is_empty() {
+        register_ue4ss_settings(&mut registry, &mut virtual_files, exe_dir, &dll_paths, &config.ue4ss_settings);
+    }
+
+    // Exclusions are checked before any mapping applies. Relative patterns start at the game root.
+    for pattern in config.exclude {
+        registry.exclude(toplevel_dir.join(pattern));
+    }
+
+    // Hooks aren't enabled yet, so this sees the real file system.
+    registry.skip_empty_originals(|dir| fs::read_dir(dir).map_or(true, |mut entries| entries.next().is_none()));
 
     let _ = PATH_REGISTRY.set(registry);
 
-    if let Err(e) = hooks::enable_hooks() {
-        panic!("Failed to enable one or more hooks. {e}")
+    hooks::enable_hooks(&config.hooks).map_err(ShimError::Hooks)?;
+
+    load_dlls(&dll_paths);
+
+    Ok(())
+}
+
+/// Serve a `mods.txt` listing every mod in `mod_dirs`, merged into the one the user already has.
+/// The user's list is the first `mods.txt` the overlay would show, the one in the mod
+/// directories or else the game's own.
+fn register_mod_list(
+    registry: &mut PathRegistry,
+    virtual_files: &mut VirtualFileStore,
+    mods_dir: &Path,
+    mod_dirs: &[PathBuf],
+) {
+    let overrides = mod_dirs
+        .iter()
+        .map(PathBuf::as_path)
+        .chain([mods_dir])
+        .map(|x| x.join("mods.txt"))
+        .find(|x| x.is_file());
+    let overrides = match overrides.map(fs::read_to_string) {
+        Some(Ok(text)) => text,
+        Some(Err(e)) => {
+            error!("Failed to read the existing mods.txt, generating it from scratch. {e}");
+            String::new()
+        }
+        None => String::new(),
+    };
+
+    let mods = ue4ss::find_mods(mod_dirs);
+    debug!("generated mods.txt with {} mods found in the mod directories: {mods:?}", mods.len());
+
+    let contents = ue4ss::merge_mod_list(&overrides, &mods);
+    let path = NormalizedPath::new(mods_dir.join("mods.txt"));
+    match virtual_files.write(&path, contents) {
+        Ok(backing) => registry.register_virtual_file(path, backing),
+        Err(e) => error!("Failed to serve the generated mods.txt. {e}"),
+    }
+}
+
+/// Serve `UE4SS-settings.ini` with `overrides` applied. The file sits next to `ue4ss.dll`, or
+/// next to the executable if UE4SS isn't among the DLLs to load.
+fn register_ue4ss_settings(
+    registry: &mut PathRegistry,
+    virtual_files: &mut VirtualFileStore,
+    exe_dir: &Path,
+    dll_paths: &[PathBuf],
+    overrides: &[SettingOverride],
+) {
+    let settings_dir = dll_paths
+        .iter()
+        .find(|x| x.file_name().is_some_and(|x| x.eq_ignore_ascii_case("ue4ss.dll")))
+        .and_then(|x| x.parent())
+        .unwrap_or(exe_dir);
+    let settings_path = settings_dir.join("UE4SS-settings.ini");
+
+    // Without an existing file, UE4SS falls back to its defaults for everything not overridden.
+    let text = match fs::read_to_string(&settings_path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            error!(
+                "Failed to read {}, applying the UE4SS setting overrides to an empty file. {e}",
+                settings_path.display()
+            );
+            String::new()
+        }
+    };
+
+    debug!("overriding {} UE4SS settings in {}", overrides.len(), settings_path.display());
+
+    let contents = ue4ss::merge_ini(&text, overrides);
+    let path = NormalizedPath::new(settings_path);
+    match virtual_files.write(&path, contents) {
+        Ok(backing) => registry.register_virtual_file(path, backing),
+        Err(e) => error!("Failed to serve the patched UE4SS-settings.ini. {e}"),
+    }
+}
+
+/// Build the configuration from `shimloader.toml` and the command line.
+/// `on_error` is updated as soon as a policy is known, so later failures in here already respect it.
+fn load_config(exe_dir: &Path, on_error: &mut ErrorPolicy) -> Result<Config, ShimError> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let mut opts = Options::new(args.iter().map(String::as_str));
+
+    let mut config_path: Option<PathBuf> = None;
+    let mut log_level: Option<LevelFilter> = None;
+    let mut error_policy: Option<ErrorPolicy> = None;
+
+    // Each directory argument may be given more than once to layer several real directories
+    // behind the same virtual one. The first occurrence receives any newly created files.
+    // Relative directories are resolved against the directory the game was launched from.
+    let launch_dir = env::current_dir().unwrap_or_else(|_| exe_dir.to_path_buf());
+    let dir_argument = |dir: &str| config::resolve_dir_argument(dir, &launch_dir);
+    let mut lua_dirs: Vec<PathBuf> = Vec::new();
+    let mut pak_dirs: Vec<PathBuf> = Vec::new();
+    let mut cfg_dirs: Vec<PathBuf> = Vec::new();
+
+    // Arbitrary `--map <virtual>=<real>` and `--hide <virtual>` mappings. Relative sources are
+    // resolved against the game root, relative targets against the directory the game was
+    // launched from, like any other path on the command line.
+    let mut maps: Vec<MappingConfig> = Vec::new();
+
+    // `--exclude <path or glob>` patterns, added to the ones from the configuration file.
+    let mut excludes: Vec<PathBuf> = Vec::new();
+
+    // `--generate-mod-list` turns on serving a generated `Mods\mods.txt`, whatever the configuration file says.
+    let mut generate_mod_list = false;
+
+    // `--ue4ss-setting <section>.<key>=<value>` overrides, applied after the ones from the configuration file.
+    let mut ue4ss_settings: Vec<SettingOverride> = Vec::new();
+
+    // Options handled here must also be listed in `cmdline::SHIM_OPTIONS` so they are hidden from the game.
+    while let Some(opt) = opts.next_arg().map_err(|e| ShimError::Args(e.to_string()))? {
+        match opt {
+            Arg::Long("shim-config") => config_path = Some(PathBuf::from(opts.value().map_err(no_value("shim-config"))?)),
+            Arg::Long("shim-log-level") => {
+                let level = opts.value().map_err(no_value("shim-log-level"))?;
+                log_level = Some(LevelFilter::from_str(level).map_err(|_| {
+                    ShimError::Args(format!("`--shim-log-level` argument {level:?} is not a valid log level."))
+                })?);
+            }
+            Arg::Long("shim-on-error") => {
+                let policy = opts.value().map_err(no_value("shim-on-error"))?;
+                let policy = ErrorPolicy::from_str(policy).map_err(|_| {
+                    ShimError::Args(format!("`--shim-on-error` argument {policy:?} is not one of abort, warn or silent."))
+                })?;
+                error_policy = Some(policy);
+                *on_error = policy;
+            }
+            Arg::Long("mod-dir") => lua_dirs.push(dir_argument(opts.value().map_err(no_value("mod-dir"))?)),
+            Arg::Long("pak-dir") => pak_dirs.push(dir_argument(opts.value().map_err(no_value("pak-dir"))?)),
+            Arg::Long("cfg-dir") => cfg_dirs.push(dir_argument(opts.value().map_err(no_value("cfg-dir"))?)),
+            Arg::Long("map") => {
+                let spec = opts.value().map_err(no_value("map"))?;
+                let mapping = MappingConfig::parse_map(spec, &launch_dir).ok_or_else(|| {
+                    ShimError::Args(format!("`--map` argument {spec:?} is not of the form <virtual>=<real>."))
+                })?;
+                maps.push(mapping);
+            }
+            Arg::Long("hide") => maps.push(MappingConfig {
+                source: PathBuf::from(opts.value().map_err(no_value("hide"))?),
+                targets: Vec::new(),
+                writable: 0,
+                hide: true,
+                copy_on_write: false,
+            }),
+            Arg::Long("exclude") => excludes.push(PathBuf::from(opts.value().map_err(no_value("exclude"))?)),
+            Arg::Long("generate-mod-list") => generate_mod_list = true,
+            Arg::Long("ue4ss-setting") => {
+                let spec = opts.value().map_err(no_value("ue4ss-setting"))?;
+                let setting = SettingOverride::parse(spec).ok_or_else(|| {
+                    ShimError::Args(format!(
+                        "`--ue4ss-setting` argument {spec:?} is not of the form <section>.<key>=<value>."
+                    ))
+                })?;
+                ue4ss_settings.push(setting);
+            }
+            _ => (),
+        }
+    }
+
+    // Load shimloader.toml, either from the path given on the command line or from next to the executable.
+    let config_path = config_path.or_else(|| {
+        Some(exe_dir.join(config::CONFIG_FILE_NAME)).filter(|x| x.is_file())
+    });
+    let mut config = match config_path {
+        Some(path) => Config::load(&path)?,
+        None => Config::default(),
+    };
+
+    // Command line arguments take precedence over the configuration file.
+    if let Some(level) = log_level {
+        config.log_level = level;
+    }
+    if let Some(policy) = error_policy {
+        config.on_error = policy;
+    }
+    *on_error = config.on_error;
+    config.generate_mod_list |= generate_mod_list;
+
+    for (dirs, cli_dirs) in [
+        (&mut config.mod_dirs, lua_dirs),
+        (&mut config.pak_dirs, pak_dirs),
+        (&mut config.cfg_dirs, cfg_dirs),
+    ] {
+        if !cli_dirs.is_empty() {
+            *dirs = cli_dirs;
+        }
     }
+    // Mappings are registered in order, so a command line mapping replaces one from the file with the same source.
+    config.mappings.extend(maps);
+    config.exclude.extend(excludes);
+    config.ue4ss_settings.extend(ue4ss_settings);
+
+    Ok(config)
+}
 
-    load_ue4ss(&current_exe);
+fn no_value<E>(option: &'static str) -> impl FnOnce(E) -> ShimError {
+    move |_| ShimError::Args(format!("`--{option}` argument has no value."))
 }
 
-unsafe fn load_ue4ss(current_exe: &Path) {
-    let ue4ss_dll = current_exe.join("../ue4ss.dll");
-    assert!(ue4ss_dll.is_file(), "ue4ss.dll could not be found at {ue4ss_dll:?}");
+/// Report a startup failure according to `policy`. Unless the policy aborts, the game
+/// carries on without any redirection.
+unsafe fn handle_init_error(error: &ShimError, policy: ErrorPolicy) {
+    error!("{error}");
 
-    let wide_path = U16CString::from_str(ue4ss_dll.to_str().unwrap()).unwrap();
-    LoadLibraryW(wide_path.as_ptr());
+    match policy {
+        ErrorPolicy::Abort => {
+            show_message(&format!("unreal-shimloader failed to start: \n\n{error}"), MB_ICONERROR);
+            std::process::abort();
+        }
+        ErrorPolicy::Warn => {
+            let message = format!("unreal-shimloader failed to start, the game will run without mods: \n\n{error}");
+            show_message(&message, MB_ICONWARNING);
+        }
+        ErrorPolicy::Silent => (),
+    }
+
+    if let Err(e) = hooks::disable_hooks() {
+        error!("Failed to disable hooks. {e}");
+    }
+}
+
+unsafe fn show_message(message: &str, style: MESSAGEBOX_STYLE) {
+    let message = U16CString::from_str_truncate(message);
+    MessageBoxW(0, message.as_ptr(), w!("unreal-shimloader"), style);
+}
+
+unsafe fn load_dlls(dll_paths: &[PathBuf]) {
+    for dll_path in dll_paths {
+        debug!("loading {}", dll_path.display());
+
+        let wide_path = paths::path_to_widestring(dll_path);
+        LoadLibraryW(wide_path.as_ptr());
+    }
 }
@@ -0,0 +1,62 @@
+use windows_sys::Win32::Foundation::{GENERIC_ALL, GENERIC_WRITE};
+use windows_sys::Win32::Storage::FileSystem::{
+    CREATE_ALWAYS, DELETE, FILE_APPEND_DATA, FILE_CREATION_DISPOSITION, FILE_OVERWRITE, FILE_OVERWRITE_IF,
+    FILE_SUPERSEDE, FILE_WRITE_ATTRIBUTES, FILE_WRITE_DATA, FILE_WRITE_EA, NT_CREATE_FILE_DISPOSITION,
+    TRUNCATE_EXISTING,
+};
+use windows_sys::Win32::System::WindowsProgramming::FILE_DELETE_ON_CLOSE;
+
+/// Access rights that count as opening a file for writing, which copy-on-write mappings copy the file for.
+/// Opening for `DELETE` counts too, since deleting or renaming through the handle changes the file.
+const WRITE_ACCESS: u32 =
+    GENERIC_WRITE | GENERIC_ALL | FILE_WRITE_DATA | FILE_APPEND_DATA | FILE_WRITE_ATTRIBUTES | FILE_WRITE_EA | DELETE;
+
+/// Whether an open may change the file it opens, either through the access it asks for or because
+/// it throws away what the file holds without asking for write access.
+fn changes_file(desired_access: u32, discards_contents: bool) -> bool {
+    desired_access & WRITE_ACCESS != 0 || discards_contents
+}
+
+/// Whether a `CreateFile` call may change the file it opens.
+pub fn opens_for_write(desired_access: u32, creation_disposition: FILE_CREATION_DISPOSITION) -> bool {
+    changes_file(desired_access, matches!(creation_disposition, CREATE_ALWAYS | TRUNCATE_EXISTING))
+}
+
+/// Whether an `NtCreateFile` or `NtOpenFile` call may change the file it opens.
+/// `NtOpenFile` takes no disposition and always opens with `FILE_OPEN`.
+pub fn nt_opens_for_write(desired_access: u32, disposition: NT_CREATE_FILE_DISPOSITION, options: u32) -> bool {
+    let discards = matches!(disposition, FILE_SUPERSEDE | FILE_OVERWRITE | FILE_OVERWRITE_IF);
+    changes_file(desired_access, discards || options & FILE_DELETE_ON_CLOSE != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use windows_sys::Win32::Storage::FileSystem::{
+        FILE_CREATE, FILE_OPEN, FILE_OPEN_IF, FILE_READ_DATA, OPEN_ALWAYS, OPEN_EXISTING,
+    };
+
+    #[test]
+    fn test_write_access_opens_for_write() {
+        assert!(opens_for_write(GENERIC_WRITE, OPEN_EXISTING));
+        assert!(nt_opens_for_write(FILE_APPEND_DATA, FILE_OPEN, 0));
+        assert!(!opens_for_write(FILE_READ_DATA, OPEN_ALWAYS));
+        assert!(!nt_opens_for_write(FILE_READ_DATA, FILE_OPEN_IF, 0));
+    }
+
+    #[test]
+    fn test_truncating_disposition_opens_for_write() {
+        assert!(opens_for_write(FILE_READ_DATA, CREATE_ALWAYS));
+        assert!(opens_for_write(FILE_READ_DATA, TRUNCATE_EXISTING));
+
+        for disposition in [FILE_SUPERSEDE, FILE_OVERWRITE, FILE_OVERWRITE_IF] {
+            assert!(nt_opens_for_write(FILE_READ_DATA, disposition, 0));
+        }
+        assert!(!nt_opens_for_write(FILE_READ_DATA, FILE_CREATE, 0));
+    }
+
+    #[test]
+    fn test_delete_on_close_opens_for_write() {
+        assert!(nt_opens_for_write(FILE_READ_DATA, FILE_OPEN, FILE_DELETE_ON_CLOSE));
+    }
+}
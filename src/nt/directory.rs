@@ -0,0 +1,284 @@
+pub const FILE_DIRECTORY_INFORMATION: u32 = 1;
+pub const FILE_FULL_DIRECTORY_INFORMATION: u32 = 2;
+pub const FILE_BOTH_DIRECTORY_INFORMATION: u32 = 3;
+pub const FILE_NAMES_INFORMATION: u32 = 12;
+pub const FILE_ID_BOTH_DIRECTORY_INFORMATION: u32 = 37;
+pub const FILE_ID_FULL_DIRECTORY_INFORMATION: u32 = 38;
+pub const FILE_ID_EXTD_DIRECTORY_INFORMATION: u32 = 60;
+pub const FILE_ID_EXTD_BOTH_DIRECTORY_INFORMATION: u32 = 63;
+
+/// Records in a listing buffer start on 8 byte boundaries.
+const ALIGNMENT: usize = 8;
+
+/// Where the file name sits in the records of one information class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordLayout {
+    name_length_offset: usize,
+    name_offset: usize,
+}
+
+impl RecordLayout {
+    /// The layout of an information class, or None if the class is not supported.
+    pub fn for_class(class: u32) -> Option<RecordLayout> {
+        let (name_length_offset, name_offset) = match class {
+            FILE_DIRECTORY_INFORMATION => (60, 64),
+            FILE_FULL_DIRECTORY_INFORMATION => (60, 68),
+            FILE_BOTH_DIRECTORY_INFORMATION => (60, 94),
+            FILE_NAMES_INFORMATION => (8, 12),
+            FILE_ID_BOTH_DIRECTORY_INFORMATION => (60, 104),
+            FILE_ID_FULL_DIRECTORY_INFORMATION => (60, 80),
+            FILE_ID_EXTD_DIRECTORY_INFORMATION => (60, 88),
+            FILE_ID_EXTD_BOTH_DIRECTORY_INFORMATION => (60, 114),
+            _ => return None,
+        };
+
+        Some(RecordLayout { name_length_offset, name_offset })
+    }
+
+    /// The size of the fixed part of a record, everything before the file name.
+    pub fn header_len(&self) -> usize {
+        self.name_offset
+    }
+}
+
+/// A single directory entry copied out of a listing buffer, with its `NextEntryOffset` cleared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectoryRecord {
+    bytes: Vec<u8>,
+    layout: RecordLayout,
+}
+
+impl DirectoryRecord {
+    pub fn name(&self) -> String {
+        let units = self.bytes[self.layout.name_offset..]
+            .chunks_exact(2)
+            .map(|x| u16::from_le_bytes([x[0], x[1]]))
+            .collect::<Vec<_>>();
+
+        String::from_utf16_lossy(&units)
+    }
+}
+
+fn read_u32(buffer: &[u8], offset: usize) -> Option<u32> {
+    let bytes = buffer.get(offset..offset + 4)?;
+    bytes.try_into().ok().map(u32::from_le_bytes)
+}
+
+/// Split a filled listing buffer into its records by following the `NextEntryOffset` chain.
+/// Returns None if the chain points outside the buffer.
+pub fn parse_records(buffer: &[u8], layout: RecordLayout) -> Option<Vec<DirectoryRecord>> {
+    let mut records = Vec::new();
+    if buffer.is_empty() {
+        return Some(records);
+    }
+
+    let mut offset = 0;
+    loop {
+        let record = buffer.get(offset..)?;
+        let next = read_u32(record, 0)? as usize;
+        let name_length = read_u32(record, layout.name_length_offset)? as usize;
+
+        let mut bytes = record.get(..layout.name_offset + name_length)?.to_vec();
+        bytes[..4].fill(0);
+        records.push(DirectoryRecord { bytes, layout });
+
+        if next == 0 {
+            break;
+        }
+        offset += next;
+    }
+
+    Some(records)
+}
+
+/// Pack records into `buffer` the way `NtQueryDirectoryFile` does.
+///
+/// Writing stops at the first record that doesn't fit, or after the first record when
+/// `single` is set. Returns the number of records written and the number of bytes used.
+///
+/// When not even the first record fits but its fixed part does, that part is written along
+/// with as much of the name as fits, and the count is 0. The name length in the record still
+/// gives the full length, so the caller can report `STATUS_BUFFER_OVERFLOW` like file systems do.
+pub fn write_records(records: &[DirectoryRecord], buffer: &mut [u8], single: bool) -> (usize, usize) {
+    let mut used: usize = 0;
+    let mut previous: Option<usize> = None;
+    let mut count = 0;
+
+    for record in records {
+        let start = used.next_multiple_of(ALIGNMENT);
+        let end = start + record.bytes.len();
+        if end > buffer.len() {
+            if count == 0 && buffer.len() >= record.layout.header_len() {
+                // Whole UTF-16 units only.
+                let name_len = (buffer.len() - record.layout.header_len()) & !1;
+                used = record.layout.header_len() + name_len;
+                buffer[..used].copy_from_slice(&record.bytes[..used]);
+            }
+            break;
+        }
+
+        buffer[start..end].copy_from_slice(&record.bytes);
+        if let Some(previous) = previous {
+            let next_offset = u32::try_from(start - previous).unwrap_or(0);
+            buffer[previous..previous + 4].copy_from_slice(&next_offset.to_le_bytes());
+        }
+
+        previous = Some(start);
+        used = end;
+        count += 1;
+
+        if single {
+            break;
+        }
+    }
+
+    (count, used)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a record with the given layout, filling the fixed part with `fill`.
+    fn record(layout: RecordLayout, name: &str, fill: u8) -> Vec<u8> {
+        let name = name.encode_utf16().flat_map(u16::to_le_bytes).collect::<Vec<_>>();
+        let mut bytes = vec![fill; layout.name_offset];
+        bytes[..4].fill(0);
+        let name_length = u32::try_from(name.len()).expect("test names are short");
+        bytes[layout.name_length_offset..layout.name_length_offset + 4].copy_from_slice(&name_length.to_le_bytes());
+        bytes.extend(name);
+        bytes
+    }
+
+    /// Chain records into a listing buffer like the kernel does.
+    fn listing(records: &[Vec<u8>]) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        let mut previous: Option<usize> = None;
+
+        for record in records {
+            buffer.resize(buffer.len().next_multiple_of(ALIGNMENT), 0xAA);
+            let start = buffer.len();
+            if let Some(previous) = previous {
+                let next = u32::try_from(start - previous).expect("test listings are small");
+                buffer[previous..previous + 4].copy_from_slice(&next.to_le_bytes());
+            }
+            buffer.extend(record);
+            previous = Some(start);
+        }
+
+        buffer
+    }
+
+    fn names(records: &[DirectoryRecord]) -> Vec<String> {
+        records.iter().map(DirectoryRecord::name).collect()
+    }
+
+    #[test]
+    fn test_unsupported_class() {
+        assert_eq!(RecordLayout::for_class(4), None);
+    }
+
+    #[test]
+    fn test_parse_names_information() {
+        let layout = RecordLayout::for_class(FILE_NAMES_INFORMATION).expect("supported class");
+        let buffer = listing(&[record(layout, ".", 1), record(layout, "..", 2), record(layout, "Keybinds.ini", 3)]);
+
+        let records = parse_records(&buffer, layout).expect("listing should parse");
+        assert_eq!(names(&records), vec![".", "..", "Keybinds.ini"]);
+    }
+
+    #[test]
+    fn test_parse_both_directory_information() {
+        let layout = RecordLayout::for_class(FILE_BOTH_DIRECTORY_INFORMATION).expect("supported class");
+        let buffer = listing(&[record(layout, "pakchunk99-Mods.pak", 7), record(layout, "Café", 8)]);
+
+        let records = parse_records(&buffer, layout).expect("listing should parse");
+        assert_eq!(names(&records), vec!["pakchunk99-Mods.pak", "Café"]);
+    }
+
+    #[test]
+    fn test_parse_empty() {
+        let layout = RecordLayout::for_class(FILE_NAMES_INFORMATION).expect("supported class");
+        assert_eq!(parse_records(&[], layout), Some(Vec::new()));
+    }
+
+    #[test]
+    fn test_parse_rejects_out_of_bounds_chain() {
+        let layout = RecordLayout::for_class(FILE_NAMES_INFORMATION).expect("supported class");
+        let mut buffer = listing(&[record(layout, "a", 0)]);
+        buffer[..4].copy_from_slice(&1000u32.to_le_bytes());
+
+        assert_eq!(parse_records(&buffer, layout), None);
+    }
+
+    #[test]
+    fn test_parse_rejects_truncated_name() {
+        let layout = RecordLayout::for_class(FILE_NAMES_INFORMATION).expect("supported class");
+        let mut buffer = record(layout, "abc", 0);
+        buffer.truncate(buffer.len() - 1);
+
+        assert_eq!(parse_records(&buffer, layout), None);
+    }
+
+    #[test]
+    fn test_write_roundtrip() {
+        let layout = RecordLayout::for_class(FILE_DIRECTORY_INFORMATION).expect("supported class");
+        let original = listing(&[record(layout, "a.lua", 1), record(layout, "bb.lua", 2), record(layout, "ccc.lua", 3)]);
+        let records = parse_records(&original, layout).expect("listing should parse");
+
+        let mut buffer = vec![0u8; 4096];
+        let (count, used) = write_records(&records, &mut buffer, false);
+
+        assert_eq!(count, 3);
+        assert_eq!(parse_records(&buffer[..used], layout), Some(records));
+    }
+
+    #[test]
+    fn test_write_stops_when_full() {
+        let layout = RecordLayout::for_class(FILE_NAMES_INFORMATION).expect("supported class");
+        let records = parse_records(&listing(&[record(layout, "first", 0), record(layout, "second", 0)]), layout)
+            .expect("listing should parse");
+
+        // Room for the first record only, the second starts at offset 24.
+        let mut buffer = vec![0u8; 30];
+        let (count, used) = write_records(&records, &mut buffer, false);
+
+        assert_eq!((count, used), (1, 22));
+        assert_eq!(read_u32(&buffer, 0), Some(0));
+    }
+
+    #[test]
+    fn test_write_single_entry() {
+        let layout = RecordLayout::for_class(FILE_NAMES_INFORMATION).expect("supported class");
+        let records = parse_records(&listing(&[record(layout, "a", 0), record(layout, "b", 0)]), layout)
+            .expect("listing should parse");
+
+        let mut buffer = vec![0u8; 4096];
+        assert_eq!(write_records(&records, &mut buffer, true).0, 1);
+    }
+
+    #[test]
+    fn test_write_truncates_name_of_first_record() {
+        let layout = RecordLayout::for_class(FILE_NAMES_INFORMATION).expect("supported class");
+        let records = parse_records(&record(layout, "name", 0), layout).expect("listing should parse");
+
+        // The fixed part and one and a half characters of the name.
+        let mut buffer = vec![0u8; 15];
+        assert_eq!(write_records(&records, &mut buffer, false), (0, 14));
+        assert_eq!(read_u32(&buffer, layout.name_length_offset), Some(8));
+        assert_eq!(&buffer[12..14], &u16::from(b'n').to_le_bytes());
+
+        // Exactly the fixed part.
+        let mut buffer = vec![0u8; 12];
+        assert_eq!(write_records(&records, &mut buffer, false), (0, 12));
+    }
+
+    #[test]
+    fn test_write_nothing_fits() {
+        let layout = RecordLayout::for_class(FILE_NAMES_INFORMATION).expect("supported class");
+        let records = parse_records(&record(layout, "name", 0), layout).expect("listing should parse");
+
+        let mut buffer = vec![0u8; 8];
+        assert_eq!(write_records(&records, &mut buffer, false), (0, 0));
+    }
+}
@@ -0,0 +1,7 @@
+mod access;
+mod directory;
+mod object_name;
+
+pub use access::{nt_opens_for_write, opens_for_write};
+pub use directory::{parse_records, write_records, DirectoryRecord, RecordLayout};
+pub use object_name::{parse_object_name, serialize_object_name, DosPrefix, ObjectName};
@@ -0,0 +1,233 @@
+/// The prefix in front of a DOS path in a native object name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DosPrefix {
+    /// `\??\`, the form Win32 functions hand to the native ones.
+    DosDevices,
+    /// `\\?\`, the verbatim Win32 form some callers pass straight through.
+    Verbatim,
+}
+
+impl DosPrefix {
+    fn as_str(self) -> &'static str {
+        match self {
+            DosPrefix::DosDevices => "\\??\\",
+            DosPrefix::Verbatim => "\\\\?\\",
+        }
+    }
+}
+
+/// A native object name, as found in `OBJECT_ATTRIBUTES.ObjectName`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ObjectName {
+    /// A DOS path behind a `\??\` or `\\?\` prefix. UNC paths are stored as `\\server\share\...`.
+    Dos { prefix: DosPrefix, path: String },
+    /// A path on a device such as `\Device\HarddiskVolume3`. The path keeps its leading backslash.
+    Device { device: String, path: String },
+    /// A name relative to the `RootDirectory` handle. Empty when the root itself is opened again.
+    Relative(String),
+}
+
+/// Strip `prefix` from the start of `name`, ignoring ASCII case.
+fn strip_prefix_ignore_case<'a>(name: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = name.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix).then(|| &name[prefix.len()..])
+}
+
+/// Parse a native object name.
+///
+/// `name` is the content of the `UNICODE_STRING`, which is not necessarily nul-terminated.
+/// Returns None for names with other prefixes such as `\BaseNamedObjects\` and names
+/// that aren't valid UTF-16.
+pub fn parse_object_name(name: &[u16]) -> Option<ObjectName> {
+    let name = String::from_utf16(name).ok()?;
+    let name = name.trim_end_matches('\0');
+
+    if !name.starts_with('\\') {
+        return Some(ObjectName::Relative(name.to_string()));
+    }
+
+    for prefix in [DosPrefix::DosDevices, DosPrefix::Verbatim] {
+        let Some(rest) = strip_prefix_ignore_case(name, prefix.as_str()) else {
+            continue;
+        };
+
+        let path = match strip_prefix_ignore_case(rest, "UNC\\") {
+            Some(unc) => format!("\\\\{unc}"),
+            None => rest.to_string(),
+        };
+
+        return Some(ObjectName::Dos { prefix, path });
+    }
+
+    let rest = strip_prefix_ignore_case(name, "\\Device\\")?;
+    let device_len = rest.find('\\').unwrap_or(rest.len());
+    if device_len == 0 {
+        return None;
+    }
+
+    let device_end = "\\Device\\".len() + device_len;
+    Some(ObjectName::Device {
+        device: name[..device_end].to_string(),
+        path: name[device_end..].to_string(),
+    })
+}
+
+impl ObjectName {
+    /// The DOS path this name refers to.
+    ///
+    /// `devices` maps drive letters such as `C:` to their device names, the way
+    /// `QueryDosDeviceW` reports them. Device names without a drive letter and relative
+    /// names give None.
+    pub fn dos_path(&self, devices: &[(String, String)]) -> Option<String> {
+        match self {
+            ObjectName::Dos { path, .. } => Some(path.clone()),
+            ObjectName::Device { device, path } => {
+                let (drive, _) = devices.iter().find(|(_, x)| x.eq_ignore_ascii_case(device))?;
+                let path = if path.is_empty() { "\\" } else { path };
+
+                Some(format!("{drive}{path}"))
+            }
+            ObjectName::Relative(_) => None,
+        }
+    }
+
+    /// The prefix to use when writing a DOS path back out in place of this name.
+    pub fn dos_prefix(&self) -> DosPrefix {
+        match self {
+            ObjectName::Dos { prefix, .. } => *prefix,
+            ObjectName::Device { .. } | ObjectName::Relative(_) => DosPrefix::DosDevices,
+        }
+    }
+}
+
+/// Write a DOS path out as a native object name behind `prefix`.
+/// UNC paths become `\??\UNC\server\share\...`.
+pub fn serialize_object_name(prefix: DosPrefix, dos_path: &str) -> Vec<u16> {
+    let name = match dos_path.strip_prefix("\\\\") {
+        Some(unc) => format!("{}UNC\\{unc}", prefix.as_str()),
+        None => format!("{}{dos_path}", prefix.as_str()),
+    };
+
+    name.encode_utf16().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().collect()
+    }
+
+    fn devices() -> Vec<(String, String)> {
+        vec![
+            ("C:".to_string(), "\\Device\\HarddiskVolume3".to_string()),
+            ("D:".to_string(), "\\Device\\HarddiskVolume5".to_string()),
+        ]
+    }
+
+    #[test]
+    fn test_parse_dos_devices() {
+        let name = parse_object_name(&wide("\\??\\C:\\Game\\Mods\\test.lua"));
+        assert_eq!(
+            name,
+            Some(ObjectName::Dos { prefix: DosPrefix::DosDevices, path: "C:\\Game\\Mods\\test.lua".to_string() })
+        );
+    }
+
+    #[test]
+    fn test_parse_verbatim() {
+        let name = parse_object_name(&wide("\\\\?\\C:\\Game\\Mods"));
+        assert_eq!(
+            name,
+            Some(ObjectName::Dos { prefix: DosPrefix::Verbatim, path: "C:\\Game\\Mods".to_string() })
+        );
+    }
+
+    #[test]
+    fn test_parse_unc() {
+        let name = parse_object_name(&wide("\\??\\UNC\\server\\share\\Mods")).expect("name should parse");
+        assert_eq!(name.dos_path(&[]), Some("\\\\server\\share\\Mods".to_string()));
+    }
+
+    #[test]
+    fn test_parse_device() {
+        let name = parse_object_name(&wide("\\Device\\HarddiskVolume3\\Game\\Mods")).expect("name should parse");
+        assert_eq!(
+            name,
+            ObjectName::Device {
+                device: "\\Device\\HarddiskVolume3".to_string(),
+                path: "\\Game\\Mods".to_string(),
+            }
+        );
+        assert_eq!(name.dos_path(&devices()), Some("C:\\Game\\Mods".to_string()));
+        assert_eq!(name.dos_prefix(), DosPrefix::DosDevices);
+    }
+
+    #[test]
+    fn test_parse_device_root() {
+        let name = parse_object_name(&wide("\\Device\\HarddiskVolume5")).expect("name should parse");
+        assert_eq!(name.dos_path(&devices()), Some("D:\\".to_string()));
+    }
+
+    #[test]
+    fn test_unknown_device_has_no_dos_path() {
+        let name = parse_object_name(&wide("\\Device\\NamedPipe\\pipe")).expect("name should parse");
+        assert_eq!(name.dos_path(&devices()), None);
+    }
+
+    #[test]
+    fn test_prefixes_are_case_insensitive() {
+        let name = parse_object_name(&wide("\\device\\harddiskvolume3\\x")).expect("name should parse");
+        assert_eq!(name.dos_path(&devices()), Some("C:\\x".to_string()));
+
+        let name = parse_object_name(&wide("\\??\\unc\\server\\share")).expect("name should parse");
+        assert_eq!(name.dos_path(&[]), Some("\\\\server\\share".to_string()));
+    }
+
+    #[test]
+    fn test_parse_relative() {
+        let name = parse_object_name(&wide("Paks\\LogicMods\\a.pak")).expect("name should parse");
+        assert_eq!(name, ObjectName::Relative("Paks\\LogicMods\\a.pak".to_string()));
+        assert_eq!(name.dos_path(&devices()), None);
+
+        assert_eq!(parse_object_name(&wide("")), Some(ObjectName::Relative(String::new())));
+    }
+
+    #[test]
+    fn test_parse_rejects_other_names() {
+        assert_eq!(parse_object_name(&wide("\\BaseNamedObjects\\x")), None);
+        assert_eq!(parse_object_name(&wide("\\??")), None);
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_utf16() {
+        let mut name = wide("\\??\\C:\\");
+        name.push(0xD800);
+        assert_eq!(parse_object_name(&name), None);
+    }
+
+    #[test]
+    fn test_parse_ignores_trailing_nul() {
+        let mut name = wide("\\??\\C:\\Game");
+        name.push(0);
+        let name = parse_object_name(&name).expect("name should parse");
+        assert_eq!(name.dos_path(&[]), Some("C:\\Game".to_string()));
+    }
+
+    #[test]
+    fn test_serialize() {
+        assert_eq!(serialize_object_name(DosPrefix::DosDevices, "D:\\Mods\\a.lua"), wide("\\??\\D:\\Mods\\a.lua"));
+        assert_eq!(serialize_object_name(DosPrefix::Verbatim, "D:\\Mods"), wide("\\\\?\\D:\\Mods"));
+        assert_eq!(serialize_object_name(DosPrefix::DosDevices, "\\\\nas\\mods\\a"), wide("\\??\\UNC\\nas\\mods\\a"));
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        for name in ["\\??\\C:\\Game\\Mods", "\\\\?\\C:\\Game", "\\??\\UNC\\server\\share\\x"] {
+            let parsed = parse_object_name(&wide(name)).expect("name should parse");
+            let path = parsed.dos_path(&[]).expect("dos names have a path");
+            assert_eq!(serialize_object_name(parsed.dos_prefix(), &path), wide(name));
+        }
+    }
+}
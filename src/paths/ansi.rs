@@ -0,0 +1,314 @@
+use std::ffi::{CStr, CString};
+use std::path::Path;
+use std::ptr;
+
+use widestring::U16String;
+use windows_sys::core::PCSTR;
+use windows_sys::Win32::Foundation::BOOL;
+use windows_sys::Win32::Globalization::{
+    IsDBCSLeadByteEx, MultiByteToWideChar, WideCharToMultiByte, CP_ACP, CP_UTF8, MB_ERR_INVALID_CHARS,
+    WC_ERR_INVALID_CHARS, WC_NO_BEST_FIT_CHARS,
+};
+use windows_sys::Win32::Storage::FileSystem::{WIN32_FIND_DATAA, WIN32_FIND_DATAW};
+
+use super::normalized::NormalizedPath;
+use super::wide::path_to_widestring;
+
+/// Encode a wide string in the given code page.
+/// When `strict` is set, returns None if the string contains characters the code page
+/// cannot represent exactly, otherwise those are replaced with a look-alike or the code page's
+/// default character.
+fn encode(wide: &[u16], code_page: u32, strict: bool) -> Option<Vec<u8>> {
+    if wide.is_empty() {
+        return Some(Vec::new());
+    }
+
+    let wide_len = i32::try_from(wide.len()).ok()?;
+
+    // UTF-8 reports unpaired surrogates through WC_ERR_INVALID_CHARS and doesn't support
+    // the default character flag, every other code page is the other way around. Those also
+    // swap characters for look-alikes, e for é, unless told not to, which names a different file.
+    let flags = match (strict, code_page == CP_UTF8) {
+        (false, _) => 0,
+        (true, true) => WC_ERR_INVALID_CHARS,
+        (true, false) => WC_NO_BEST_FIT_CHARS,
+    };
+    let mut used_default: BOOL = 0;
+    let used_default_ptr = if code_page == CP_UTF8 || !strict {
+        ptr::null_mut()
+    } else {
+        ptr::addr_of_mut!(used_default)
+    };
+
+    unsafe {
+        let size = WideCharToMultiByte(
+            code_page,
+            flags,
+            wide.as_ptr(),
+            wide_len,
+            ptr::null_mut(),
+            0,
+            ptr::null(),
+            used_default_ptr,
+        );
+        if size <= 0 {
+            return None;
+        }
+
+        let mut buffer = vec![0u8; usize::try_from(size).ok()?];
+        let written = WideCharToMultiByte(
+            code_page,
+            flags,
+            wide.as_ptr(),
+            wide_len,
+            buffer.as_mut_ptr(),
+            size,
+            ptr::null(),
+            used_default_ptr,
+        );
+        if written <= 0 || used_default != 0 {
+            return None;
+        }
+
+        buffer.truncate(usize::try_from(written).ok()?);
+        Some(buffer)
+    }
+}
+
+/// Encode a wide string in the given code page.
+/// Returns None if the string contains characters the code page cannot represent.
+pub fn encode_ansi(wide: &[u16], code_page: u32) -> Option<Vec<u8>> {
+    encode(wide, code_page, true)
+}
+
+/// Encode a wide string in the given code page, replacing characters it cannot represent
+/// the same way the A-suffixed Windows functions do.
+pub fn encode_ansi_lossy(wide: &[u16], code_page: u32) -> Vec<u8> {
+    encode(wide, code_page, false).unwrap_or_default()
+}
+
+/// Decode a string in the given code page to a wide string.
+/// Returns None if the bytes are not valid in the code page.
+pub fn decode_ansi(bytes: &[u8], code_page: u32) -> Option<Vec<u16>> {
+    if bytes.is_empty() {
+        return Some(Vec::new());
+    }
+
+    let bytes_len = i32::try_from(bytes.len()).ok()?;
+
+    unsafe {
+        let size = MultiByteToWideChar(
+            code_page,
+            MB_ERR_INVALID_CHARS,
+            bytes.as_ptr(),
+            bytes_len,
+            ptr::null_mut(),
+            0,
+        );
+        if size <= 0 {
+            return None;
+        }
+
+        let mut buffer = vec![0u16; usize::try_from(size).ok()?];
+        let written = MultiByteToWideChar(
+            code_page,
+            MB_ERR_INVALID_CHARS,
+            bytes.as_ptr(),
+            bytes_len,
+            buffer.as_mut_ptr(),
+            size,
+        );
+        if written <= 0 {
+            return None;
+        }
+
+        buffer.truncate(usize::try_from(written).ok()?);
+        Some(buffer)
+    }
+}
+
+/// Convert a raw PCSTR in the active code page to a normalized path.
+/// Returns None for null pointers and strings that don't decode.
+pub fn pcstr_to_path(pcstr: PCSTR) -> Option<NormalizedPath> {
+    if pcstr.is_null() {
+        return None;
+    }
+
+    let bytes = unsafe { CStr::from_ptr(pcstr.cast()) }.to_bytes();
+    let wide = decode_ansi(bytes, CP_ACP)?;
+
+    Some(NormalizedPath::new(U16String::from_vec(wide).to_string().ok()?))
+}
+
+/// Convert a path to a nul-terminated string in the given code page.
+/// Returns None if the path can't be represented in it.
+pub fn path_to_ansi(path: &Path, code_page: u32) -> Option<CString> {
+    let wide = path_to_widestring(path);
+    let bytes = encode_ansi(wide.as_slice(), code_page)?;
+
+    CString::new(bytes).ok()
+}
+
+/// Copy a nul-terminated wide name into a fixed size ANSI buffer, truncating it if needed.
+fn copy_name<const N: usize>(wide: &[u16], code_page: u32) -> [u8; N] {
+    let len = wide.iter().position(|x| *x == 0).unwrap_or(wide.len());
+    let encoded = encode_ansi_lossy(&wide[..len], code_page);
+
+    // Always leave room for the terminator.
+    let mut name = [0u8; N];
+    let count = char_boundary(&encoded, N.saturating_sub(1), code_page);
+    name[..count].copy_from_slice(&encoded[..count]);
+    name
+}
+
+/// The length of the longest prefix of `bytes` that is at most `limit` bytes long and doesn't
+/// end halfway through a character of the given code page.
+fn char_boundary(bytes: &[u8], limit: usize, code_page: u32) -> usize {
+    if bytes.len() <= limit {
+        return bytes.len();
+    }
+
+    // UTF-8 continuation bytes look like 10xxxxxx, and the code page has no lead bytes.
+    if code_page == CP_UTF8 {
+        return (0..=limit).rev().find(|&i| bytes[i] & 0xC0 != 0x80).unwrap_or(0);
+    }
+
+    let mut end = 0;
+    while end < limit {
+        let len = if unsafe { IsDBCSLeadByteEx(code_page, bytes[end]) } == 0 { 1 } else { 2 };
+        if end + len > limit {
+            break;
+        }
+        end += len;
+    }
+    end
+}
+
+/// Convert a wide find result into the ANSI form returned by `FindFirstFileA`.
+pub fn find_data_to_ansi(data: &WIN32_FIND_DATAW, code_page: u32) -> WIN32_FIND_DATAA {
+    WIN32_FIND_DATAA {
+        dwFileAttributes: data.dwFileAttributes,
+        ftCreationTime: data.ftCreationTime,
+        ftLastAccessTime: data.ftLastAccessTime,
+        ftLastWriteTime: data.ftLastWriteTime,
+        nFileSizeHigh: data.nFileSizeHigh,
+        nFileSizeLow: data.nFileSizeLow,
+        dwReserved0: data.dwReserved0,
+        dwReserved1: data.dwReserved1,
+        cFileName: copy_name(&data.cFileName, code_page),
+        cAlternateFileName: copy_name(&data.cAlternateFileName, code_page),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().collect()
+    }
+
+    fn find_data(name: &str) -> WIN32_FIND_DATAW {
+        let mut data: WIN32_FIND_DATAW = unsafe { std::mem::zeroed() };
+        let name = wide(name);
+        data.cFileName[..name.len()].copy_from_slice(&name);
+        data.nFileSizeLow = 42;
+        data
+    }
+
+    #[test]
+    fn test_encode_ascii() {
+        let result = encode_ansi(&wide("C:\\Game\\Mods\\test.lua"), 1252);
+        assert_eq!(result, Some(b"C:\\Game\\Mods\\test.lua".to_vec()));
+    }
+
+    #[test]
+    fn test_encode_code_page_specific() {
+        assert_eq!(encode_ansi(&wide("Café"), 1252), Some(b"Caf\xE9".to_vec()));
+        assert_eq!(encode_ansi(&wide("Café"), CP_UTF8), Some("Café".as_bytes().to_vec()));
+    }
+
+    #[test]
+    fn test_encode_unrepresentable() {
+        assert_eq!(encode_ansi(&wide("日本"), 1252), None);
+        assert_eq!(encode_ansi(&[0xD800], CP_UTF8), None);
+    }
+
+    #[test]
+    fn test_encode_has_no_best_fit() {
+        // Both have a best fit look-alike in the code page, but open a different file.
+        assert_eq!(encode_ansi(&wide("Café"), 1251), None);
+        assert_eq!(encode_ansi(&wide("Mods\\Ā"), 1252), None);
+        assert_eq!(path_to_ansi(Path::new("C:\\Mods\\Café"), 1251), None);
+    }
+
+    #[test]
+    fn test_encode_lossy_replaces_unrepresentable() {
+        assert_eq!(encode_ansi_lossy(&wide("a日b"), 1252), b"a?b".to_vec());
+        assert_eq!(encode_ansi_lossy(&wide("Café"), 1252), b"Caf\xE9".to_vec());
+    }
+
+    #[test]
+    fn test_encode_double_byte_code_page() {
+        let shift_jis = encode_ansi(&wide("日本"), 932).expect("932 should represent kanji");
+        assert_eq!(shift_jis, b"\x93\xFA\x96\x7B".to_vec());
+        assert_eq!(decode_ansi(&shift_jis, 932), Some(wide("日本")));
+    }
+
+    #[test]
+    fn test_decode_roundtrip() {
+        assert_eq!(decode_ansi(b"Caf\xE9", 1252), Some(wide("Café")));
+        assert_eq!(path_to_ansi(Path::new("C:\\Mods\\Café"), 1252), CString::new(b"C:\\Mods\\Caf\xE9".to_vec()).ok());
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        assert_eq!(decode_ansi(b"\xFF\xFE", CP_UTF8), None);
+        // A lead byte without its trail byte.
+        assert_eq!(decode_ansi(b"\x93", 932), None);
+    }
+
+    #[test]
+    fn test_path_to_ansi_unrepresentable() {
+        assert_eq!(path_to_ansi(Path::new("C:\\Mods\\日本"), 1252), None);
+    }
+
+    #[test]
+    fn test_pcstr_to_path() {
+        let raw = CString::new("C:\\Game\\Mods\\test.lua").expect("no interior nul");
+        let path = pcstr_to_path(raw.as_ptr().cast()).expect("ascii should decode");
+        assert_eq!(path.original(), Path::new("C:\\Game\\Mods\\test.lua"));
+        assert!(pcstr_to_path(ptr::null()).is_none());
+    }
+
+    #[test]
+    fn test_find_data_to_ansi() {
+        let data = find_data_to_ansi(&find_data("Café.pak"), 1252);
+        assert_eq!(&data.cFileName[..9], b"Caf\xE9.pak\0");
+        assert_eq!(data.cAlternateFileName[0], 0);
+        assert_eq!(data.nFileSizeLow, 42);
+    }
+
+    #[test]
+    fn test_find_data_to_ansi_truncates() {
+        let long_name = "a".repeat(259);
+        let data = find_data_to_ansi(&find_data(&long_name), CP_UTF8);
+        assert_eq!(data.cFileName[258], b'a');
+        assert_eq!(data.cFileName[259], 0);
+    }
+
+    #[test]
+    fn test_find_data_to_ansi_truncates_on_character_boundary() {
+        // 130 double-byte characters, the last of which straddles the terminator.
+        let data = find_data_to_ansi(&find_data(&"日".repeat(130)), 932);
+        assert_eq!(&data.cFileName[256..259], b"\x93\xFA\0");
+
+        let data = find_data_to_ansi(&find_data(&"é".repeat(130)), CP_UTF8);
+        assert_eq!(&data.cFileName[256..259], b"\xC3\xA9\0");
+    }
+
+    #[test]
+    fn test_encode_empty() {
+        assert_eq!(encode_ansi(&[], 1252), Some(Vec::new()));
+    }
+}
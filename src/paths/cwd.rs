@@ -0,0 +1,124 @@
+use std::path::{Component, Path, PathBuf};
+use std::sync::{PoisonError, RwLock};
+
+use super::normalized::NormalizedPath;
+
+/// The current directory as the game sees it, set while the real one is a redirect target.
+static VIRTUAL_CWD: RwLock<Option<NormalizedPath>> = RwLock::new(None);
+
+pub fn virtual_cwd() -> Option<NormalizedPath> {
+    VIRTUAL_CWD.read().unwrap_or_else(PoisonError::into_inner).clone()
+}
+
+/// Replace the virtual current directory. None means the real one is what the game sees.
+pub fn set_virtual_cwd(cwd: Option<NormalizedPath>) {
+    *VIRTUAL_CWD.write().unwrap_or_else(PoisonError::into_inner) = cwd;
+}
+
+/// Resolve `path` against `cwd` the way Windows resolves relative paths against the current directory.
+///
+/// Handles plain relative paths, root-relative paths like `\Mods` and drive-relative paths like
+/// `C:Mods` on the drive of `cwd`. Returns None for absolute paths and for paths relative to
+/// the current directory of another drive, which the shim doesn't track.
+pub fn resolve_relative(path: &Path, cwd: &NormalizedPath) -> Option<NormalizedPath> {
+    let mut components = path.components();
+
+    match components.next()? {
+        Component::Prefix(prefix) => {
+            if components.next() == Some(Component::RootDir) {
+                return None;
+            }
+
+            // Only a drive letter can be followed by a relative path.
+            let Some(Component::Prefix(cwd_prefix)) = cwd.inner().components().next() else {
+                return None;
+            };
+            if !prefix.as_os_str().eq_ignore_ascii_case(cwd_prefix.as_os_str()) {
+                return None;
+            }
+
+            let rest = path.components().skip(1).collect::<PathBuf>();
+            Some(cwd.join(rest))
+        }
+        Component::RootDir => {
+            let Some(Component::Prefix(cwd_prefix)) = cwd.original().components().next() else {
+                return None;
+            };
+
+            Some(NormalizedPath::new(Path::new(cwd_prefix.as_os_str()).join(path)))
+        }
+        Component::CurDir | Component::ParentDir | Component::Normal(_) => Some(cwd.join(path)),
+    }
+}
+
+/// Make `path` absolute against the virtual current directory, if one is set.
+pub fn absolute(path: &NormalizedPath) -> Option<NormalizedPath> {
+    absolute_in(path, &virtual_cwd()?)
+}
+
+/// Make `path` absolute against `cwd`.
+///
+/// An empty path comes out of normalization as `.`, so both are left alone rather than turned
+/// into the current directory.
+fn absolute_in(path: &NormalizedPath, cwd: &NormalizedPath) -> Option<NormalizedPath> {
+    if path.original().as_os_str().is_empty() || path.original() == Path::new(".") {
+        return None;
+    }
+
+    resolve_relative(path.original(), cwd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cwd() -> NormalizedPath {
+        NormalizedPath::new("C:\\Game\\Binaries\\Win64\\Mods\\Keybinds")
+    }
+
+    #[test]
+    fn test_resolve_plain_relative() {
+        let result = resolve_relative(Path::new("Scripts\\main.lua"), &cwd());
+        assert_eq!(
+            result,
+            Some(NormalizedPath::new("C:\\Game\\Binaries\\Win64\\Mods\\Keybinds\\Scripts\\main.lua"))
+        );
+    }
+
+    #[test]
+    fn test_resolve_parent_and_current() {
+        let result = resolve_relative(Path::new("..\\shared\\.\\Types.lua"), &cwd());
+        assert_eq!(result, Some(NormalizedPath::new("C:\\Game\\Binaries\\Win64\\Mods\\shared\\Types.lua")));
+    }
+
+    #[test]
+    fn test_resolve_root_relative() {
+        let result = resolve_relative(Path::new("\\Other\\file.txt"), &cwd());
+        assert_eq!(result, Some(NormalizedPath::new("C:\\Other\\file.txt")));
+    }
+
+    #[test]
+    fn test_resolve_drive_relative() {
+        let result = resolve_relative(Path::new("c:Scripts"), &cwd());
+        assert_eq!(result, Some(NormalizedPath::new("C:\\Game\\Binaries\\Win64\\Mods\\Keybinds\\Scripts")));
+
+        assert_eq!(resolve_relative(Path::new("D:Scripts"), &cwd()), None);
+    }
+
+    #[test]
+    fn test_absolute_paths_are_left_alone() {
+        assert_eq!(resolve_relative(Path::new("D:\\MyMods\\main.lua"), &cwd()), None);
+        assert_eq!(resolve_relative(Path::new("\\\\server\\share\\x"), &cwd()), None);
+        assert_eq!(resolve_relative(Path::new(""), &cwd()), None);
+    }
+
+    #[test]
+    fn test_absolute_leaves_empty_paths_alone() {
+        assert_eq!(absolute_in(&NormalizedPath::new(""), &cwd()), None);
+        assert_eq!(absolute_in(&NormalizedPath::new("."), &cwd()), None);
+        assert_eq!(
+            absolute_in(&NormalizedPath::new("Scripts"), &cwd()),
+            Some(NormalizedPath::new("C:\\Game\\Binaries\\Win64\\Mods\\Keybinds\\Scripts"))
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use std::path::{Component, Path, PathBuf};
+
+use super::normalized::NormalizedPath;
+
+/// A path pattern matched component by component, ignoring case.
+///
+/// `*` matches any run of characters inside a component, `?` matches a single character and
+/// a `**` component matches any number of components, including none. Every wildcard captures
+/// the text it matched, numbered from 1 in the order the wildcards appear.
+#[derive(Debug, Clone)]
+pub struct Glob {
+    components: Vec<Vec<char>>,
+    /// The components in front of the first wildcard.
+    literal_prefix: NormalizedPath,
+    has_wildcards: bool,
+}
+
+fn is_wildcard(c: char) -> bool {
+    c == '*' || c == '?'
+}
+
+fn original_components(path: &NormalizedPath) -> Vec<Vec<char>> {
+    path.original()
+        .components()
+        .map(|x| x.as_os_str().to_string_lossy().chars().collect())
+        .collect()
+}
+
+fn chars_eq_ignore_case(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+/// Match a single component against a pattern component, pushing what each wildcard matched.
+/// `*` is greedy, so with several ways to match the earlier wildcards take the most.
+fn match_component(pattern: &[char], name: &[char], captures: &mut Vec<String>) -> bool {
+    let depth = captures.len();
+
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => {
+            for take in (0..=name.len()).rev() {
+                captures.push(name[..take].iter().collect());
+                if match_component(rest, &name[take..], captures) {
+                    return true;
+                }
+                captures.truncate(depth);
+            }
+            false
+        }
+        Some(('?', rest)) => {
+            let Some((first, name)) = name.split_first() else {
+                return false;
+            };
+
+            captures.push(first.to_string());
+            if match_component(rest, name, captures) {
+                return true;
+            }
+            captures.truncate(depth);
+            false
+        }
+        Some((c, rest)) => name
+            .split_first()
+            .is_some_and(|(first, name)| chars_eq_ignore_case(*c, *first) && match_component(rest, name, captures)),
+    }
+}
+
+/// Match every component of `names` against the pattern components.
+fn match_components(patterns: &[Vec<char>], names: &[Vec<char>], captures: &mut Vec<String>) -> bool {
+    let depth = captures.len();
+
+    match patterns.split_first() {
+        None => names.is_empty(),
+        Some((pattern, rest)) if pattern.as_slice() == ['*', '*'] => {
+            for take in (0..=names.len()).rev() {
+                let joined = names[..take].iter().map(|x| x.iter().collect::<String>()).collect::<Vec<_>>();
+                captures.push(joined.join("\\"));
+                if match_components(rest, &names[take..], captures) {
+                    return true;
+                }
+                captures.truncate(depth);
+            }
+            false
+        }
+        Some((pattern, rest)) => {
+            let Some((name, names)) = names.split_first() else {
+                return false;
+            };
+
+            if match_component(pattern, name, captures) && match_components(rest, names, captures) {
+                return true;
+            }
+            captures.truncate(depth);
+            false
+        }
+    }
+}
+
+impl Glob {
+    pub fn new(pattern: impl Into<NormalizedPath>) -> Self {
+        let pattern = pattern.into();
+
+        // Verbatim prefixes contain a `?` that isn't a wildcard.
+        let is_pattern_component = |x: &Component| {
+            matches!(x, Component::Normal(_)) && x.as_os_str().to_string_lossy().contains(is_wildcard)
+        };
+        let has_wildcards = pattern.original().components().any(|x| is_pattern_component(&x));
+        let literal_prefix = pattern
+            .original()
+            .components()
+            .take_while(|x| !is_pattern_component(x))
+            .collect::<PathBuf>();
+
+        Glob {
+            components: original_components(&pattern),
+            literal_prefix: NormalizedPath::new(literal_prefix),
+            has_wildcards,
+        }
+    }
+
+    /// Whether the pattern contains any wildcards, rather than being a plain path.
+    pub fn has_wildcards(&self) -> bool {
+        self.has_wildcards
+    }
+
+    /// The part of the pattern in front of the first wildcard.
+    pub fn literal_prefix(&self) -> &NormalizedPath {
+        &self.literal_prefix
+    }
+
+    /// Whether the whole of `path` matches the pattern.
+    pub fn matches(&self, path: &NormalizedPath) -> bool {
+        match_components(&self.components, &original_components(path), &mut Vec::new())
+    }
+
+    /// Whether `path` or one of its ancestors matches the pattern, so a pattern naming a
+    /// directory covers everything inside it.
+    pub fn matches_ancestor(&self, path: &NormalizedPath) -> bool {
+        let names = original_components(path);
+        (1..=names.len()).any(|len| match_components(&self.components, &names[..len], &mut Vec::new()))
+    }
+
+    /// Match the pattern against `path` or its closest matching ancestor.
+    /// Returns the captures and the rest of `path` below the part that matched.
+    pub fn captures(&self, path: &NormalizedPath) -> Option<(Vec<String>, PathBuf)> {
+        let names = original_components(path);
+
+        (1..=names.len()).rev().find_map(|len| {
+            let mut captures = Vec::new();
+            if !match_components(&self.components, &names[..len], &mut captures) {
+                return None;
+            }
+
+            let rest = path.original().components().skip(len).collect::<PathBuf>();
+            Some((captures, rest))
+        })
+    }
+}
+
+/// Fill the `{1}`, `{2}`, ... placeholders in a target template with the captures of a glob.
+/// Placeholders without a matching capture are left as they are.
+pub fn expand_template(template: &Path, captures: &[String]) -> PathBuf {
+    let mut expanded = template.to_string_lossy().into_owned();
+    for (i, capture) in captures.iter().enumerate() {
+        expanded = expanded.replace(&format!("{{{}}}", i + 1), capture);
+    }
+
+    PathBuf::from(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        Glob::new(pattern).matches(&NormalizedPath::new(path))
+    }
+
+    fn captures(pattern: &str, path: &str) -> Option<(Vec<String>, PathBuf)> {
+        Glob::new(pattern).captures(&NormalizedPath::new(path))
+    }
+
+    #[test]
+    fn test_literal() {
+        assert!(matches("C:\\Game\\Config\\DefaultGame.ini", "c:\\game\\config\\defaultgame.ini"));
+        assert!(!matches("C:\\Game\\Config\\DefaultGame.ini", "C:\\Game\\Config\\DefaultEngine.ini"));
+        assert!(!matches("C:\\Game\\Config", "C:\\Game\\Config\\DefaultGame.ini"));
+        assert!(!Glob::new("C:\\Game\\Config").has_wildcards());
+    }
+
+    #[test]
+    fn test_star() {
+        assert!(matches("C:\\Game\\Config\\Default*.ini", "C:\\Game\\Config\\DefaultGame.ini"));
+        assert!(matches("C:\\Game\\Config\\*", "C:\\Game\\Config\\Input.ini"));
+        assert!(!matches("C:\\Game\\Config\\*.ini", "C:\\Game\\Config\\Windows\\Input.ini"));
+    }
+
+    #[test]
+    fn test_question_mark() {
+        assert!(matches("C:\\Game\\pakchunk?-Mods.pak", "C:\\Game\\pakchunk9-Mods.pak"));
+        assert!(!matches("C:\\Game\\pakchunk?-Mods.pak", "C:\\Game\\pakchunk99-Mods.pak"));
+    }
+
+    #[test]
+    fn test_double_star() {
+        assert!(matches("C:\\Game\\Mods\\**\\*.bak", "C:\\Game\\Mods\\a.bak"));
+        assert!(matches("C:\\Game\\Mods\\**\\*.bak", "C:\\Game\\Mods\\Keybinds\\Scripts\\main.bak"));
+        assert!(!matches("C:\\Game\\Mods\\**\\*.bak", "C:\\Game\\Other\\a.bak"));
+    }
+
+    #[test]
+    fn test_matches_ancestor() {
+        let glob = Glob::new("C:\\Game\\Mods\\shared");
+        assert!(glob.matches_ancestor(&NormalizedPath::new("C:\\Game\\Mods\\shared")));
+        assert!(glob.matches_ancestor(&NormalizedPath::new("C:\\Game\\Mods\\shared\\Types.lua")));
+        assert!(!glob.matches_ancestor(&NormalizedPath::new("C:\\Game\\Mods\\sharedstuff\\x.lua")));
+        assert!(!glob.matches_ancestor(&NormalizedPath::new("C:\\Game\\Mods")));
+    }
+
+    #[test]
+    fn test_captures_keep_case() {
+        let result = captures("C:\\Game\\Content\\Paks\\*.pak", "c:\\game\\content\\paks\\MyMod_P.pak");
+        assert_eq!(result, Some((vec!["MyMod_P".to_string()], PathBuf::new())));
+    }
+
+    #[test]
+    fn test_captures_with_rest() {
+        let result = captures("C:\\Game\\Mods\\*\\Scripts", "C:\\Game\\Mods\\Keybinds\\Scripts\\lib\\main.lua");
+        assert_eq!(result, Some((vec!["Keybinds".to_string()], PathBuf::from("lib\\main.lua"))));
+    }
+
+    #[test]
+    fn test_captures_double_star() {
+        let result = captures("C:\\Game\\Mods\\*\\Scripts\\**", "C:\\Game\\Mods\\Keybinds\\Scripts\\lib\\main.lua");
+        assert_eq!(
+            result,
+            Some((vec!["Keybinds".to_string(), "lib\\main.lua".to_string()], PathBuf::new()))
+        );
+    }
+
+    #[test]
+    fn test_captures_no_match() {
+        assert_eq!(captures("C:\\Game\\Content\\Paks\\*.pak", "C:\\Game\\Content\\Paks\\a.sig"), None);
+    }
+
+    #[test]
+    fn test_literal_prefix() {
+        let glob = Glob::new("C:\\Game\\Mods\\*\\Scripts\\**");
+        assert!(glob.has_wildcards());
+        assert_eq!(glob.literal_prefix(), &NormalizedPath::new("C:\\Game\\Mods"));
+    }
+
+    #[test]
+    fn test_expand_template() {
+        let captures = vec!["Keybinds".to_string(), "lib\\main.lua".to_string()];
+        assert_eq!(
+            expand_template(Path::new("D:\\Scripts\\{1}\\{2}"), &captures),
+            PathBuf::from("D:\\Scripts\\Keybinds\\lib\\main.lua")
+        );
+        assert_eq!(expand_template(Path::new("D:\\Paks\\{3}"), &captures), PathBuf::from("D:\\Paks\\{3}"));
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::path::Path;
+
+/// Split a `FindFirstFile` search string into the directory being searched and the file pattern.
+/// Returns None if the search string has no directory or file name component.
+pub fn split_search_pattern(search: &Path) -> Option<(&Path, &OsStr)> {
+    let pattern = search.file_name()?;
+    let directory = search.parent().filter(|x| !x.as_os_str().is_empty())?;
+
+    Some((directory, pattern))
+}
+
+/// Merge directory listings from several layers into a single listing.
+/// Earlier layers shadow later ones: entries are deduplicated by name, case-insensitively,
+/// keeping the first occurrence.
+pub fn merge_listings<T>(layers: Vec<Vec<T>>, name_of: impl Fn(&T) -> String) -> Vec<T> {
+    let mut seen = HashSet::new();
+
+    layers
+        .into_iter()
+        .flatten()
+        .filter(|entry| seen.insert(name_of(entry).to_lowercase()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listing(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_split_search_pattern() {
+        let search = Path::new("C:\\Game\\Mods\\*");
+        let result = split_search_pattern(search);
+        assert_eq!(result, Some((Path::new("C:\\Game\\Mods"), OsStr::new("*"))));
+    }
+
+    #[test]
+    fn test_split_search_pattern_without_directory() {
+        assert_eq!(split_search_pattern(Path::new("*.pak")), None);
+    }
+
+    #[test]
+    fn test_merge_keeps_entries_from_all_layers() {
+        let merged = merge_listings(
+            vec![listing(&["a.pak", "b.pak"]), listing(&["c.pak"])],
+            Clone::clone,
+        );
+        assert_eq!(merged, listing(&["a.pak", "b.pak", "c.pak"]));
+    }
+
+    #[test]
+    fn test_merge_dedups_case_insensitively() {
+        let merged = merge_listings(
+            vec![listing(&[".", "..", "Shared"]), listing(&[".", "..", "shared", "Keybinds"])],
+            Clone::clone,
+        );
+        assert_eq!(merged, listing(&[".", "..", "Shared", "Keybinds"]));
+    }
+
+    #[test]
+    fn test_merge_earlier_layer_wins() {
+        let merged = merge_listings(
+            vec![vec![("Mod.pak", 1)], vec![("MOD.PAK", 2)]],
+            |(name, _)| (*name).to_string(),
+        );
+        assert_eq!(merged, vec![("Mod.pak", 1)]);
+    }
+
+    #[test]
+    fn test_merge_empty_layers() {
+        let merged = merge_listings(vec![vec![], listing(&["a.pak"]), vec![]], Clone::clone);
+        assert_eq!(merged, listing(&["a.pak"]));
+    }
+}
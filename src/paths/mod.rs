@@ -1,9 +1,22 @@
+mod ansi;
+mod cwd;
+mod glob;
+mod listing;
 mod normalized;
 mod registry;
 mod splice;
+mod trie;
+mod virtual_files;
 mod wide;
 
+pub use ansi::{decode_ansi, encode_ansi, encode_ansi_lossy, find_data_to_ansi, path_to_ansi, pcstr_to_path};
+pub use cwd::{set_virtual_cwd, virtual_cwd};
+pub use listing::{merge_listings, split_search_pattern};
 pub use normalized::NormalizedPath;
 pub use registry::{PathRegistry, PATH_REGISTRY};
-pub use splice::remap_path;
+pub use splice::{
+    bypass_remapping, copy_on_write, hides_inside, is_hidden, is_original_only, lookup_layers, overlaps_mapping,
+    remap_path, remap_path_for_write, reverse_remap_path,
+};
+pub use virtual_files::VirtualFileStore;
 pub use wide::{path_to_widestring, pcwstr_to_path};
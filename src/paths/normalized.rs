@@ -1,6 +1,6 @@
 use std::fmt::{Debug, Formatter};
 use std::hash::{Hash, Hasher};
-use std::path::{Component, Path, PathBuf};
+use std::path::{Component, Path, PathBuf, Prefix};
 
 fn clean_path(path: impl AsRef<Path>) -> PathBuf {
     let mut out: Vec<Component> = Vec::new();
@@ -13,10 +13,9 @@ fn clean_path(path: impl AsRef<Path>) -> PathBuf {
                 Some(Component::Normal(_)) => {
                     out.pop();
                 }
-                None
-                | Some(Component::CurDir)
-                | Some(Component::ParentDir)
-                | Some(Component::Prefix(_)) => out.push(comp),
+                None | Some(Component::CurDir | Component::ParentDir | Component::Prefix(_)) => {
+                    out.push(comp);
+                }
             },
             comp => out.push(comp),
         }
@@ -71,13 +70,49 @@ impl NormalizedPath {
         self.inner.starts_with(&base.inner)
     }
 
+    /// Strip `prefix` from this path, comparing case-insensitively.
+    /// The returned remainder keeps the casing of the original path.
     pub fn strip_prefix(&self, prefix: &NormalizedPath) -> Option<PathBuf> {
-        self.inner.strip_prefix(&prefix.inner).ok().map(Path::to_path_buf)
+        if !self.starts_with(prefix) {
+            return None;
+        }
+
+        // Lowercasing never adds or removes separators, so the component counts of
+        // `inner` and `original` always line up.
+        let remainder = self
+            .original
+            .components()
+            .skip(prefix.component_count())
+            .collect();
+
+        Some(remainder)
     }
 
     pub fn join(&self, path: impl AsRef<Path>) -> NormalizedPath {
         NormalizedPath::new(self.original.join(path))
     }
+
+    /// Whether both paths are on the same drive or network share, judging by their prefixes.
+    /// Mount points and junctions aren't taken into account.
+    pub fn same_volume(&self, other: &NormalizedPath) -> bool {
+        self.volume() == other.volume()
+    }
+
+    fn volume(&self) -> Option<String> {
+        let Some(Component::Prefix(prefix)) = self.inner.components().next() else {
+            return None;
+        };
+
+        let volume = match prefix.kind() {
+            Prefix::Disk(drive) | Prefix::VerbatimDisk(drive) => char::from(drive).to_ascii_lowercase().to_string(),
+            Prefix::UNC(server, share) | Prefix::VerbatimUNC(server, share) => {
+                format!("{}\\{}", server.to_string_lossy(), share.to_string_lossy())
+            }
+            _ => prefix.as_os_str().to_string_lossy().into_owned(),
+        };
+
+        Some(volume)
+    }
 }
 
 impl Debug for NormalizedPath {
@@ -161,12 +196,50 @@ mod tests {
         assert_eq!(remainder, Some(PathBuf::from("test.lua")));
     }
 
+    #[test]
+    fn test_strip_prefix_preserves_case() {
+        let parent = NormalizedPath::new("c:\\game\\mods");
+        let child = NormalizedPath::new("C:\\Game\\Mods\\Other\\Test.lua");
+
+        let remainder = child.strip_prefix(&parent);
+        assert_eq!(remainder, Some(PathBuf::from("Other\\Test.lua")));
+    }
+
+    #[test]
+    fn test_strip_prefix_exact_match_is_empty() {
+        let parent = NormalizedPath::new("C:\\Game\\Mods");
+        let child = NormalizedPath::new("C:\\Game\\MODS");
+
+        let remainder = child.strip_prefix(&parent);
+        assert_eq!(remainder, Some(PathBuf::new()));
+    }
+
+    #[test]
+    fn test_same_volume() {
+        let game = NormalizedPath::new("C:\\Game\\Config\\Engine.ini");
+        let verbatim = NormalizedPath::new("\\\\?\\c:\\Game\\Config\\Engine.ini.tmp");
+        let other_drive = NormalizedPath::new("D:\\Profiles\\Config\\Engine.ini");
+
+        assert!(game.same_volume(&verbatim));
+        assert!(!game.same_volume(&other_drive));
+    }
+
+    #[test]
+    fn test_same_volume_unc() {
+        let share = NormalizedPath::new("\\\\server\\share\\a.txt");
+        let same_share = NormalizedPath::new("\\\\SERVER\\Share\\b\\c.txt");
+        let other_share = NormalizedPath::new("\\\\server\\other\\a.txt");
+
+        assert!(share.same_volume(&same_share));
+        assert!(!share.same_volume(&other_share));
+    }
+
     // Edge case tests for Windows path handling
 
     #[test]
     fn test_forward_slashes_normalized() {
         let path = NormalizedPath::new("C:/Game/Mods/test.lua");
-        assert!(path.inner().to_string_lossy().contains("\\"));
+        assert!(path.inner().to_string_lossy().contains('\\'));
     }
 
     #[test]
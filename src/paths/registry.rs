@@ -1,67 +1,440 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 
 use log::debug;
 
+use super::glob::{expand_template, Glob};
 use super::normalized::NormalizedPath;
 use super::splice::splice_path;
+use super::trie::PathTrie;
 
 pub static PATH_REGISTRY: OnceLock<PathRegistry> = OnceLock::new();
 
+/// What a path is about to be used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Reads in copy-on-write mappings may fall through to files that only exist in the original
+    /// directory.
+    Read,
+    /// Anything that creates, changes or removes files stays inside the mapping's layers.
+    Write,
+}
+
+/// A virtual source directory backed by one or more real directories.
+///
+/// Layers are searched in order. Paths that exist in none of them resolve to the writable layer
+/// so that newly created files land there, and the original directory is hidden behind them.
+///
+/// A source containing wildcards is a pattern, and its layers are templates that may refer to
+/// the segments the wildcards matched as `{1}`, `{2}` and so on.
+///
+/// A copy-on-write mapping shows through to the original directory instead. Files that only exist
+/// there are read where they are and copied into the writable layer the first time they are
+/// opened for writing.
 pub struct PathMapping {
     source: NormalizedPath,
-    target: NormalizedPath,
+    pattern: Option<Glob>,
+    layers: Vec<NormalizedPath>,
+    writable: usize,
+    copy_on_write: bool,
+    /// Whether reads of files found in no layer fall through to the original directory.
+    falls_through: bool,
 }
 
 impl PathMapping {
     pub fn new(source: impl Into<NormalizedPath>, target: impl Into<NormalizedPath>) -> Self {
+        PathMapping::overlay(source, vec![target.into()], 0)
+    }
+
+    /// Create a mapping backed by several ordered layers.
+    /// `writable` is an index into `layers` and is clamped to the last layer.
+    pub fn overlay(source: impl Into<NormalizedPath>, layers: Vec<NormalizedPath>, writable: usize) -> Self {
+        let writable = writable.min(layers.len().saturating_sub(1));
+        let source = source.into();
+        let pattern = Some(Glob::new(source.clone())).filter(Glob::has_wildcards);
+
         PathMapping {
-            source: source.into(),
-            target: target.into(),
+            source,
+            pattern,
+            layers,
+            writable,
+            copy_on_write: false,
+            falls_through: false,
         }
     }
+
+    /// Whether `path` is the source or inside it.
+    fn covers(&self, path: &NormalizedPath) -> bool {
+        match &self.pattern {
+            Some(pattern) => pattern.matches_ancestor(path),
+            None => path.starts_with(&self.source),
+        }
+    }
+
+    fn resolve(&self, path: &NormalizedPath, access: Access, exists: impl Fn(&Path) -> bool) -> Option<PathBuf> {
+        let candidates = self.layers(path)?;
+        if access == Access::Write && candidates.len() == 1 {
+            return candidates.into_iter().next();
+        }
+
+        if let Some(found) = candidates.iter().position(|candidate| exists(candidate)) {
+            return candidates.into_iter().nth(found);
+        }
+
+        // Files that only exist in the original directory of a copy-on-write mapping may still be
+        // read where they are.
+        if access == Access::Read && self.falls_through && exists(path.original()) {
+            return None;
+        }
+
+        candidates.into_iter().nth(self.writable)
+    }
+
+    /// Where the original file at `path` has to be copied before it is written to.
+    /// Returns None unless this is a copy-on-write mapping and the file exists nowhere but
+    /// in the original directory.
+    fn copy_target(&self, path: &NormalizedPath, exists: impl Fn(&Path) -> bool) -> Option<PathBuf> {
+        if !self.copy_on_write || !self.original_only(path, exists) {
+            return None;
+        }
+
+        self.layers(path)?.into_iter().nth(self.writable)
+    }
+
+    /// Whether `path` exists in the original directory but in none of the layers.
+    fn original_only(&self, path: &NormalizedPath, exists: impl Fn(&Path) -> bool) -> bool {
+        self.falls_through
+            && self.layers(path).is_some_and(|candidates| {
+                !candidates.iter().any(|candidate| exists(candidate)) && exists(path.original())
+            })
+    }
+
+    fn layers(&self, path: &NormalizedPath) -> Option<Vec<PathBuf>> {
+        let Some(pattern) = &self.pattern else {
+            return self
+                .layers
+                .iter()
+                .map(|layer| splice_path(path, &self.source, layer))
+                .collect();
+        };
+
+        let (captures, rest) = pattern.captures(path)?;
+        let layers = self.layers.iter().map(|layer| {
+            let target = expand_template(layer.original(), &captures);
+            if rest.as_os_str().is_empty() { target } else { target.join(&rest) }
+        });
+
+        Some(layers.collect())
+    }
 }
 
 /// Registry of virtual path mappings.
+///
+/// When several mappings cover a path, the most specific one wins. Literal sources are compared
+/// by length, and a pattern counts as long as the literal part before its first wildcard, winning
+/// ties with literal sources. Among the patterns that apply, the first one registered wins.
 pub struct PathRegistry {
-    mappings: Vec<PathMapping>,
+    mappings: PathTrie<PathMapping>,
+    patterns: Vec<PathMapping>,
+    /// Paths that always come from the original location, even inside a mapping.
+    exclusions: Vec<Glob>,
+    /// Paths that look absent, whatever is on disk.
+    hidden: Vec<Glob>,
+    /// Every layer of every mapping, keyed by the layer and holding it along with its source.
+    layers: PathTrie<(NormalizedPath, NormalizedPath)>,
+    /// Files registered in memory, keyed by their virtual path and holding the file backing them.
+    virtual_files: HashMap<NormalizedPath, NormalizedPath>,
+    /// Directories holding the backing files, keyed by the virtual directory the files appear in.
+    virtual_file_dirs: HashMap<NormalizedPath, PathBuf>,
 }
 
 impl PathRegistry {
     pub fn new() -> Self {
         PathRegistry {
-            mappings: Vec::new(),
+            mappings: PathTrie::new(),
+            patterns: Vec::new(),
+            exclusions: Vec::new(),
+            hidden: Vec::new(),
+            layers: PathTrie::new(),
+            virtual_files: HashMap::new(),
+            virtual_file_dirs: HashMap::new(),
         }
     }
 
     pub fn register(&mut self, source: impl Into<NormalizedPath>, target: impl Into<NormalizedPath>) {
-        let mapping = PathMapping::new(source, target);
+        self.insert(PathMapping::new(source, target));
+    }
+
+    /// Register a source backed by several ordered target directories.
+    /// An overlay without any layers is ignored.
+    pub fn register_overlay<T: Into<NormalizedPath>>(
+        &mut self,
+        source: impl Into<NormalizedPath>,
+        layers: impl IntoIterator<Item = T>,
+        writable: usize,
+    ) {
+        let layers = layers.into_iter().map(Into::into).collect::<Vec<_>>();
+        if layers.is_empty() {
+            return;
+        }
+
+        self.insert(PathMapping::overlay(source, layers, writable));
+    }
+
+    /// Register an overlay that leaves original files in place until they are written to,
+    /// then copies them into the writable layer. An overlay without any layers is ignored.
+    pub fn register_copy_on_write<T: Into<NormalizedPath>>(
+        &mut self,
+        source: impl Into<NormalizedPath>,
+        layers: impl IntoIterator<Item = T>,
+        writable: usize,
+    ) {
+        let layers = layers.into_iter().map(Into::into).collect::<Vec<_>>();
+        if layers.is_empty() {
+            return;
+        }
+
+        let mut mapping = PathMapping::overlay(source, layers, writable);
+        mapping.copy_on_write = true;
+        mapping.falls_through = true;
+        self.insert(mapping);
+    }
+
+    fn insert(&mut self, mapping: PathMapping) {
         debug!(
-            "[PathRegistry] Registered mapping: {:?} -> {:?}",
-            mapping.source, mapping.target
+            "[PathRegistry] Registered mapping: {:?} -> {:?} (writable: {:?}, copy-on-write: {})",
+            mapping.source,
+            mapping.layers,
+            mapping.layers.get(mapping.writable),
+            mapping.copy_on_write
         );
-        self.mappings.push(mapping);
+
+        if mapping.pattern.is_some() {
+            if let Some(previous) = self.patterns.iter_mut().find(|x| x.source == mapping.source) {
+                debug!(
+                    "[PathRegistry] Replaced existing mapping: {:?} -> {:?}",
+                    previous.source, previous.layers
+                );
+                *previous = mapping;
+            } else {
+                self.patterns.push(mapping);
+            }
+            return;
+        }
+
+        let source = mapping.source.clone();
+        for layer in &mapping.layers {
+            self.layers.insert(layer, (layer.clone(), source.clone()));
+        }
+
+        if let Some(previous) = self.mappings.insert(&source, mapping) {
+            debug!(
+                "[PathRegistry] Replaced existing mapping: {:?} -> {:?}",
+                previous.source, previous.layers
+            );
+        }
     }
 
-    pub fn try_remap(&self, path: &NormalizedPath) -> Option<PathBuf> {
-        for mapping in &self.mappings {
-            if let Some(remapped) = splice_path(path, &mapping.source, &mapping.target) {
-                return Some(remapped);
+    /// Stop looking in the original directory of literal copy-on-write mappings whose source `is_empty`.
+    ///
+    /// Writes inside a mapping never reach its original directory, so a source that is missing or
+    /// empty once everything is registered stays that way. Reads of files found in no layer then
+    /// resolve without checking the original, which saves a file system call on every miss.
+    pub fn skip_empty_originals(&mut self, is_empty: impl Fn(&Path) -> bool) {
+        self.mappings.for_each_mut(|mapping| {
+            if mapping.falls_through && is_empty(mapping.source.original()) {
+                debug!("[PathRegistry] {:?} is empty, reads won't fall through to it", mapping.source);
+                mapping.falls_through = false;
             }
+        });
+    }
+
+    /// Make the file at `backing` appear at `path` without it existing there on disk.
+    ///
+    /// Opens of `path` are redirected to `backing`, and the directory holding `backing` is listed
+    /// along with the directory of `path`. Virtual files take precedence over mappings, exclusions
+    /// and hidden paths. Registering the same path again replaces its backing file.
+    pub fn register_virtual_file(&mut self, path: impl Into<NormalizedPath>, backing: impl Into<PathBuf>) {
+        let path = path.into();
+        let backing = backing.into();
+        if let (Some(parent), Some(backing_dir)) = (path.original().parent(), backing.parent()) {
+            self.virtual_file_dirs.insert(NormalizedPath::new(parent), backing_dir.to_path_buf());
         }
-        None
+
+        debug!(
+            "[PathRegistry] Registered virtual file: {} backed by {}",
+            path.original().display(),
+            backing.display()
+        );
+        self.virtual_files.insert(path, NormalizedPath::new(backing));
+    }
+
+    /// Keep paths matching `pattern` out of every mapping. The pattern may be an exact path or a
+    /// glob, and a pattern naming a directory excludes everything inside it.
+    pub fn exclude(&mut self, pattern: impl Into<NormalizedPath>) {
+        let pattern = pattern.into();
+        debug!("[PathRegistry] Registered exclusion: {pattern:?}");
+
+        self.exclusions.push(Glob::new(pattern));
+    }
+
+    pub fn is_excluded(&self, path: &NormalizedPath) -> bool {
+        self.exclusions.iter().any(|x| x.matches_ancestor(path))
+    }
+
+    /// Make paths matching `pattern` look absent. The pattern may be an exact path or a glob,
+    /// and a pattern naming a directory hides everything inside it.
+    pub fn hide(&mut self, pattern: impl Into<NormalizedPath>) {
+        let pattern = pattern.into();
+        debug!("[PathRegistry] Registered hidden path: {pattern:?}");
+
+        self.hidden.push(Glob::new(pattern));
+    }
+
+    pub fn is_hidden(&self, path: &NormalizedPath) -> bool {
+        !self.virtual_files.contains_key(path) && self.hidden.iter().any(|x| x.matches_ancestor(path))
+    }
+
+    /// Whether listing the directory at `path` may have to leave hidden entries out.
+    pub fn hides_inside(&self, path: &NormalizedPath) -> bool {
+        self.hidden.iter().any(|x| {
+            let prefix = x.literal_prefix();
+            let is_child = prefix.starts_with(path) && prefix.component_count() <= path.component_count() + 1;
+
+            is_child || path.starts_with(prefix)
+        })
+    }
+
+    pub fn try_remap(&self, path: &NormalizedPath, access: Access) -> Option<PathBuf> {
+        self.resolve(path, access, Path::exists)
+    }
+
+    /// The mapping that applies to `path`, if any.
+    fn mapping_for(&self, path: &NormalizedPath) -> Option<&PathMapping> {
+        let literal = self.mappings.longest_prefix(path);
+        let literal_len = literal.map_or(0, |x| x.source.component_count());
+
+        // Only patterns whose literal prefix is inside `path` and at least as long as the literal
+        // source are worth matching.
+        self.patterns
+            .iter()
+            .filter(|x| {
+                x.pattern.as_ref().is_some_and(|pattern| {
+                    let prefix = pattern.literal_prefix();
+                    prefix.component_count() >= literal_len && path.starts_with(prefix)
+                })
+            })
+            .find(|x| x.covers(path))
+            .or(literal)
+    }
+
+    fn resolve(&self, path: &NormalizedPath, access: Access, exists: impl Fn(&Path) -> bool) -> Option<PathBuf> {
+        if let Some(backing) = self.virtual_files.get(path) {
+            return Some(backing.to_path_buf());
+        }
+
+        let mapping = self.mapping_for(path)?;
+        if self.is_excluded(path) {
+            return None;
+        }
+
+        mapping.resolve(path, access, exists)
+    }
+
+    /// Where the file at `path` has to be copied before it is opened for writing, if it is inside
+    /// a copy-on-write mapping and hasn't been copied yet.
+    pub fn copy_on_write_target(&self, path: &NormalizedPath, exists: impl Fn(&Path) -> bool) -> Option<PathBuf> {
+        let mapping = self.mapping_for(path)?;
+        if self.is_excluded(path) || self.virtual_files.contains_key(path) {
+            return None;
+        }
+
+        mapping.copy_target(path, exists)
+    }
+
+    /// Whether `path` is inside a mapping but only exists in its original directory.
+    /// Removing or renaming such a path would change the original, which mappings never do.
+    pub fn is_original_only(&self, path: &NormalizedPath, exists: impl Fn(&Path) -> bool) -> bool {
+        if self.is_excluded(path) || self.virtual_files.contains_key(path) {
+            return false;
+        }
+
+        self.mapping_for(path).is_some_and(|x| x.original_only(path, exists))
+    }
+
+    /// Every real location backing `path` in priority order, ending with `path` itself unless a
+    /// mapping hides it. A directory holding virtual files starts with the directory of their
+    /// backing files. Returns None if `path` is not covered by a mapping or is excluded from it,
+    /// and holds no virtual files.
+    pub fn lookup_layers(&self, path: &NormalizedPath) -> Option<Vec<PathBuf>> {
+        let virtual_file_dir = self.virtual_file_dirs.get(path);
+        let mapping = if self.is_excluded(path) { None } else { self.mapping_for(path) };
+        let mapped = mapping.and_then(|x| x.layers(path));
+        if virtual_file_dir.is_none() && mapped.is_none() {
+            return None;
+        }
+
+        let mut layers = virtual_file_dir.cloned().into_iter().collect::<Vec<_>>();
+        layers.extend(mapped.unwrap_or_default());
+        if mapping.is_none_or(|x| x.falls_through) {
+            layers.push(path.to_path_buf());
+        }
+
+        Some(layers)
+    }
+
+    /// Map a real path inside one of the layers back to the virtual path the game knows it by.
+    /// Returns None if `path` isn't inside a layer. Layers of pattern mappings are templates and
+    /// aren't mapped back.
+    pub fn reverse_remap(&self, path: &NormalizedPath) -> Option<PathBuf> {
+        if let Some((virtual_path, _)) = self.virtual_files.iter().find(|(_, backing)| *backing == path) {
+            return Some(virtual_path.to_path_buf());
+        }
+
+        let (layer, source) = self.layers.longest_prefix(path)?;
+
+        // Layers of a mapping that has since been replaced stay in the trie, skip those.
+        let mapping = self.mappings.longest_prefix(source)?;
+        if mapping.source != *source || !mapping.layers.contains(layer) {
+            return None;
+        }
+
+        splice_path(path, layer, source)
+    }
+
+    /// Whether `path` is covered by a mapping or contains the source of one, a hidden path or a
+    /// virtual file.
+    pub fn overlaps(&self, path: &NormalizedPath) -> bool {
+        let overlaps_pattern = self.patterns.iter().filter_map(|x| x.pattern.as_ref()).any(|x| {
+            path.starts_with(x.literal_prefix()) || x.literal_prefix().starts_with(path)
+        });
+
+        let overlaps_hidden = self
+            .hidden
+            .iter()
+            .any(|x| path.starts_with(x.literal_prefix()) || x.literal_prefix().starts_with(path));
+
+        let overlaps_virtual_file =
+            self.virtual_files.contains_key(path) || self.virtual_file_dirs.keys().any(|x| x.starts_with(path));
+
+        overlaps_pattern
+            || overlaps_hidden
+            || overlaps_virtual_file
+            || self.mappings.longest_prefix(path).is_some()
+            || self.mappings.has_descendants(path)
     }
 
     pub fn would_remap(&self, path: &NormalizedPath) -> bool {
-        self.try_remap(path).is_some()
+        self.try_remap(path, Access::Read).is_some()
     }
 
     pub fn len(&self) -> usize {
-        self.mappings.len()
+        self.mappings.len() + self.patterns.len() + self.virtual_files.len()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.mappings.is_empty()
+        self.mappings.is_empty() && self.patterns.is_empty() && self.virtual_files.is_empty()
     }
 }
 
@@ -76,11 +449,11 @@ mod tests {
         registry.register("C:\\Game\\Content\\Paks\\LogicMods", "D:\\MyPaks");
 
         let path = NormalizedPath::new("C:\\Game\\Mods\\test.lua");
-        let result = registry.try_remap(&path);
+        let result = registry.try_remap(&path, Access::Read);
         assert_eq!(result, Some(PathBuf::from("D:\\MyMods\\test.lua")));
 
         let path = NormalizedPath::new("C:\\Game\\Content\\Paks\\LogicMods\\mod.pak");
-        let result = registry.try_remap(&path);
+        let result = registry.try_remap(&path, Access::Read);
         assert_eq!(result, Some(PathBuf::from("D:\\MyPaks\\mod.pak")));
     }
 
@@ -90,26 +463,517 @@ mod tests {
         registry.register("C:\\Game\\Mods", "D:\\MyMods");
 
         let path = NormalizedPath::new("C:\\Other\\file.txt");
-        let result = registry.try_remap(&path);
+        let result = registry.try_remap(&path, Access::Read);
         assert_eq!(result, None);
     }
 
     #[test]
-    fn test_registry_first_match_wins() {
+    fn test_registry_most_specific_wins() {
         let mut registry = PathRegistry::new();
-        // More specific mapping first
         registry.register("C:\\Game\\Mods\\Special", "D:\\SpecialMods");
         registry.register("C:\\Game\\Mods", "D:\\MyMods");
 
-        // Path under Special should use first mapping
+        // Path under Special should use the more specific mapping
         let path = NormalizedPath::new("C:\\Game\\Mods\\Special\\test.lua");
-        let result = registry.try_remap(&path);
+        let result = registry.try_remap(&path, Access::Read);
         assert_eq!(result, Some(PathBuf::from("D:\\SpecialMods\\test.lua")));
 
-        // Path under Mods (not Special) should use second mapping
-        // Note: result path is lowercase due to NormalizedPath normalization
+        // Path under Mods (not Special) should use the broader mapping
         let path = NormalizedPath::new("C:\\Game\\Mods\\Other\\test.lua");
-        let result = registry.try_remap(&path);
-        assert_eq!(result, Some(PathBuf::from("D:\\MyMods\\other\\test.lua")));
+        let result = registry.try_remap(&path, Access::Read);
+        assert_eq!(result, Some(PathBuf::from("D:\\MyMods\\Other\\test.lua")));
+    }
+
+    #[test]
+    fn test_registry_most_specific_wins_regardless_of_order() {
+        let mut registry = PathRegistry::new();
+        // Broader mapping registered first
+        registry.register("C:\\Game\\Mods", "D:\\MyMods");
+        registry.register("C:\\Game\\Mods\\Special", "D:\\SpecialMods");
+
+        let path = NormalizedPath::new("C:\\Game\\Mods\\Special\\test.lua");
+        let result = registry.try_remap(&path, Access::Read);
+        assert_eq!(result, Some(PathBuf::from("D:\\SpecialMods\\test.lua")));
+    }
+
+    #[test]
+    fn test_registry_register_replaces_same_source() {
+        let mut registry = PathRegistry::new();
+        registry.register("C:\\Game\\Mods", "D:\\OldMods");
+        registry.register("c:\\game\\mods", "D:\\NewMods");
+
+        assert_eq!(registry.len(), 1);
+
+        let path = NormalizedPath::new("C:\\Game\\Mods\\test.lua");
+        let result = registry.try_remap(&path, Access::Read);
+        assert_eq!(result, Some(PathBuf::from("D:\\NewMods\\test.lua")));
+    }
+
+    #[test]
+    fn test_overlay_first_existing_layer_wins() {
+        let mut registry = PathRegistry::new();
+        registry.register_overlay(
+            "C:\\Game\\Content\\Paks\\LogicMods",
+            ["D:\\Packages\\A", "D:\\Packages\\B"],
+            0,
+        );
+
+        let exists = |path: &Path| path == Path::new("D:\\Packages\\B\\b.pak");
+        let path = NormalizedPath::new("C:\\Game\\Content\\Paks\\LogicMods\\b.pak");
+        let result = registry.resolve(&path, Access::Read, exists);
+        assert_eq!(result, Some(PathBuf::from("D:\\Packages\\B\\b.pak")));
+    }
+
+    #[test]
+    fn test_overlay_missing_file_uses_writable_layer() {
+        let mut registry = PathRegistry::new();
+        registry.register_overlay(
+            "C:\\Game\\Content\\Paks\\LogicMods",
+            ["D:\\Packages\\A", "D:\\Packages\\B"],
+            1,
+        );
+
+        let path = NormalizedPath::new("C:\\Game\\Content\\Paks\\LogicMods\\new.pak");
+        let result = registry.resolve(&path, Access::Read, |_| false);
+        assert_eq!(result, Some(PathBuf::from("D:\\Packages\\B\\new.pak")));
+    }
+
+    #[test]
+    fn test_overlay_earlier_layer_shadows_later() {
+        let mut registry = PathRegistry::new();
+        registry.register_overlay("C:\\Game\\Mods", ["D:\\A", "D:\\B"], 1);
+
+        let path = NormalizedPath::new("C:\\Game\\Mods\\shared.lua");
+        let result = registry.resolve(&path, Access::Read, |_| true);
+        assert_eq!(result, Some(PathBuf::from("D:\\A\\shared.lua")));
+    }
+
+    #[test]
+    fn test_overlay_hides_files_only_in_original() {
+        let mut registry = PathRegistry::new();
+        registry.register("C:\\Game\\Mods", "D:\\MyMods");
+
+        let exists = |path: &Path| path == Path::new("C:\\Game\\Mods\\shared\\Types.lua");
+        let path = NormalizedPath::new("C:\\Game\\Mods\\shared\\Types.lua");
+        assert_eq!(
+            registry.resolve(&path, Access::Read, exists),
+            Some(PathBuf::from("D:\\MyMods\\shared\\Types.lua"))
+        );
+        assert!(!registry.is_original_only(&path, exists));
+    }
+
+    #[test]
+    fn test_copy_on_write_shows_files_only_in_original() {
+        let mut registry = PathRegistry::new();
+        registry.register_copy_on_write("C:\\Game\\Mods", ["D:\\MyMods"], 0);
+
+        let exists = |path: &Path| path == Path::new("C:\\Game\\Mods\\shared\\Types.lua");
+        let path = NormalizedPath::new("C:\\Game\\Mods\\shared\\Types.lua");
+        assert_eq!(registry.resolve(&path, Access::Read, exists), None);
+
+        // Files in the layer still shadow the original.
+        let exists = |path: &Path| path.starts_with("C:\\Game") || path.starts_with("D:\\MyMods");
+        assert_eq!(
+            registry.resolve(&path, Access::Read, exists),
+            Some(PathBuf::from("D:\\MyMods\\shared\\Types.lua"))
+        );
+    }
+
+    #[test]
+    fn test_writes_never_reach_the_original() {
+        let mut registry = PathRegistry::new();
+        registry.register_overlay("C:\\Game\\Mods", ["D:\\A", "D:\\B"], 1);
+
+        let exists = |path: &Path| path == Path::new("C:\\Game\\Mods\\shared\\Types.lua");
+        let path = NormalizedPath::new("C:\\Game\\Mods\\shared\\Types.lua");
+        assert_eq!(
+            registry.resolve(&path, Access::Write, exists),
+            Some(PathBuf::from("D:\\B\\shared\\Types.lua"))
+        );
+
+        let exists = |path: &Path| path == Path::new("D:\\A\\shared\\Types.lua");
+        assert_eq!(
+            registry.resolve(&path, Access::Write, exists),
+            Some(PathBuf::from("D:\\A\\shared\\Types.lua"))
+        );
+    }
+
+    #[test]
+    fn test_empty_original_is_not_checked() {
+        let mut registry = PathRegistry::new();
+        registry.register_copy_on_write("C:\\Game\\Mods", ["D:\\MyMods"], 0);
+        registry.register_copy_on_write("C:\\Game\\Content\\Paks\\LogicMods", ["D:\\MyPaks"], 0);
+        registry.skip_empty_originals(|dir| dir == Path::new("C:\\Game\\Content\\Paks\\LogicMods"));
+
+        // Only the original directory would claim to have the files, which an empty one can't.
+        let exists = |x: &Path| x.starts_with("C:\\Game");
+        let path = NormalizedPath::new("C:\\Game\\Content\\Paks\\LogicMods\\mod.pak");
+        assert_eq!(registry.resolve(&path, Access::Read, exists), Some(PathBuf::from("D:\\MyPaks\\mod.pak")));
+        assert!(!registry.is_original_only(&path, exists));
+
+        let path = NormalizedPath::new("C:\\Game\\Mods\\shared\\Types.lua");
+        assert_eq!(registry.resolve(&path, Access::Read, exists), None);
+    }
+
+    #[test]
+    fn test_original_only_file_under_write_access() {
+        let mut registry = PathRegistry::new();
+        registry.register_copy_on_write("C:\\Game\\Mods", ["D:\\MyMods"], 0);
+        registry.exclude("C:\\Game\\Mods\\shared\\Excluded.lua");
+
+        // A write resolves to the layer, where there is nothing to remove or rename.
+        let original = Path::new("C:\\Game\\Mods\\shared\\Types.lua");
+        let path = NormalizedPath::new("C:\\Game\\Mods\\shared\\Types.lua");
+        let exists = |x: &Path| x == original;
+        assert_eq!(
+            registry.resolve(&path, Access::Write, exists),
+            Some(PathBuf::from("D:\\MyMods\\shared\\Types.lua"))
+        );
+        assert!(registry.is_original_only(&path, exists));
+
+        let exists = |x: &Path| x == original || x == Path::new("D:\\MyMods\\shared\\Types.lua");
+        assert!(!registry.is_original_only(&path, exists));
+
+        let path = NormalizedPath::new("C:\\Game\\Mods\\shared\\Excluded.lua");
+        assert!(!registry.is_original_only(&path, |_| true));
+
+        let path = NormalizedPath::new("C:\\Game\\Content\\Paks\\pakchunk0.pak");
+        assert!(!registry.is_original_only(&path, |_| true));
+    }
+
+    #[test]
+    fn test_lookup_layers_leaves_out_hidden_original() {
+        let mut registry = PathRegistry::new();
+        registry.register_overlay("C:\\Game\\Mods", ["D:\\A", "D:\\B"], 0);
+
+        let path = NormalizedPath::new("C:\\Game\\Mods\\Sub");
+        let layers = registry.lookup_layers(&path);
+        assert_eq!(layers, Some(vec![PathBuf::from("D:\\A\\Sub"), PathBuf::from("D:\\B\\Sub")]));
+
+        let path = NormalizedPath::new("C:\\Other");
+        assert_eq!(registry.lookup_layers(&path), None);
+    }
+
+    #[test]
+    fn test_lookup_layers_ends_with_original_for_copy_on_write() {
+        let mut registry = PathRegistry::new();
+        registry.register_copy_on_write("C:\\Game\\Config", ["D:\\A", "D:\\B"], 0);
+
+        let path = NormalizedPath::new("C:\\Game\\Config\\Sub");
+        let layers = registry.lookup_layers(&path);
+        assert_eq!(
+            layers,
+            Some(vec![
+                PathBuf::from("D:\\A\\Sub"),
+                PathBuf::from("D:\\B\\Sub"),
+                PathBuf::from("C:\\Game\\Config\\Sub"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_excluded_file_is_not_remapped() {
+        let mut registry = PathRegistry::new();
+        registry.register("C:\\Game\\Config", "D:\\MyConfig");
+        registry.exclude("C:\\Game\\Config\\DefaultGame.ini");
+
+        let path = NormalizedPath::new("C:\\Game\\Config\\defaultgame.ini");
+        assert_eq!(registry.resolve(&path, Access::Read, |_| true), None);
+
+        let path = NormalizedPath::new("C:\\Game\\Config\\DefaultEngine.ini");
+        assert_eq!(
+            registry.resolve(&path, Access::Read, |_| true),
+            Some(PathBuf::from("D:\\MyConfig\\DefaultEngine.ini"))
+        );
+    }
+
+    #[test]
+    fn test_excluded_directory_covers_contents() {
+        let mut registry = PathRegistry::new();
+        registry.register_overlay("C:\\Game\\Mods", ["D:\\A", "D:\\B"], 0);
+        registry.exclude("C:\\Game\\Mods\\shared");
+
+        let path = NormalizedPath::new("C:\\Game\\Mods\\shared\\Types.lua");
+        assert_eq!(registry.resolve(&path, Access::Read, |_| false), None);
+        assert_eq!(registry.lookup_layers(&NormalizedPath::new("C:\\Game\\Mods\\shared")), None);
+
+        let path = NormalizedPath::new("C:\\Game\\Mods\\Keybinds\\main.lua");
+        assert_eq!(registry.resolve(&path, Access::Read, |_| false), Some(PathBuf::from("D:\\A\\Keybinds\\main.lua")));
+    }
+
+    #[test]
+    fn test_excluded_glob() {
+        let mut registry = PathRegistry::new();
+        registry.register("C:\\Game\\Content\\Paks\\LogicMods", "D:\\MyPaks");
+        registry.exclude("C:\\Game\\Content\\Paks\\LogicMods\\**\\*.bak");
+
+        let path = NormalizedPath::new("C:\\Game\\Content\\Paks\\LogicMods\\Sub\\a.bak");
+        assert_eq!(registry.resolve(&path, Access::Read, |_| true), None);
+
+        let path = NormalizedPath::new("C:\\Game\\Content\\Paks\\LogicMods\\a.pak");
+        assert_eq!(registry.resolve(&path, Access::Read, |_| true), Some(PathBuf::from("D:\\MyPaks\\a.pak")));
+    }
+
+    #[test]
+    fn test_pattern_single_file() {
+        let mut registry = PathRegistry::new();
+        registry.register("C:\\Game\\Content\\Paks\\*.pak", "D:\\Paks\\{1}.pak");
+
+        let path = NormalizedPath::new("C:\\Game\\Content\\Paks\\MyMod_P.pak");
+        assert_eq!(registry.resolve(&path, Access::Read, |_| true), Some(PathBuf::from("D:\\Paks\\MyMod_P.pak")));
+
+        let path = NormalizedPath::new("C:\\Game\\Content\\Paks\\MyMod_P.sig");
+        assert_eq!(registry.resolve(&path, Access::Read, |_| true), None);
+    }
+
+    #[test]
+    fn test_pattern_per_mod_subfolder() {
+        let mut registry = PathRegistry::new();
+        registry.register("C:\\Game\\Mods", "D:\\MyMods");
+        registry.register("C:\\Game\\Mods\\*\\Scripts", "E:\\Scripts\\{1}");
+
+        let path = NormalizedPath::new("C:\\Game\\Mods\\Keybinds\\Scripts\\main.lua");
+        assert_eq!(
+            registry.resolve(&path, Access::Read, |_| true),
+            Some(PathBuf::from("E:\\Scripts\\Keybinds\\main.lua"))
+        );
+
+        // Everything else in the mod folder still goes through the literal mapping.
+        let path = NormalizedPath::new("C:\\Game\\Mods\\Keybinds\\enabled.txt");
+        assert_eq!(
+            registry.resolve(&path, Access::Read, |_| true),
+            Some(PathBuf::from("D:\\MyMods\\Keybinds\\enabled.txt"))
+        );
+    }
+
+    #[test]
+    fn test_longer_literal_source_beats_pattern() {
+        let mut registry = PathRegistry::new();
+        registry.register("C:\\Game\\Mods\\*\\Scripts", "E:\\Scripts\\{1}");
+        registry.register("C:\\Game\\Mods\\Keybinds", "D:\\Keybinds");
+
+        let path = NormalizedPath::new("C:\\Game\\Mods\\Keybinds\\Scripts\\main.lua");
+        let result = registry.resolve(&path, Access::Read, |_| true);
+        assert_eq!(result, Some(PathBuf::from("D:\\Keybinds\\Scripts\\main.lua")));
+
+        let path = NormalizedPath::new("C:\\Game\\Mods\\Zoom\\Scripts\\main.lua");
+        let result = registry.resolve(&path, Access::Read, |_| true);
+        assert_eq!(result, Some(PathBuf::from("E:\\Scripts\\Zoom\\main.lua")));
+    }
+
+    #[test]
+    fn test_pattern_double_star_capture() {
+        let mut registry = PathRegistry::new();
+        registry.register("C:\\Game\\Mods\\*\\Scripts\\**", "E:\\{1}\\lua\\{2}");
+
+        let path = NormalizedPath::new("C:\\Game\\Mods\\Keybinds\\Scripts\\lib\\util.lua");
+        assert_eq!(
+            registry.resolve(&path, Access::Read, |_| true),
+            Some(PathBuf::from("E:\\Keybinds\\lua\\lib\\util.lua"))
+        );
+    }
+
+    #[test]
+    fn test_pattern_overlaps_its_parent() {
+        let mut registry = PathRegistry::new();
+        registry.register("C:\\Game\\Content\\Paks\\*.pak", "D:\\Paks\\{1}.pak");
+
+        assert!(registry.overlaps(&NormalizedPath::new("C:\\Game\\Content")));
+        assert!(!registry.overlaps(&NormalizedPath::new("C:\\Game\\Config")));
+        assert_eq!(registry.len(), 1);
+    }
+
+    #[test]
+    fn test_copy_on_write() {
+        let mut registry = PathRegistry::new();
+        registry.register_copy_on_write("C:\\Game\\Config", ["D:\\Profile\\Config"], 0);
+
+        let original = NormalizedPath::new("C:\\Game\\Config\\DefaultGame.ini");
+        let copy = PathBuf::from("D:\\Profile\\Config\\DefaultGame.ini");
+
+        // Reads fall through to the original until the file is copied.
+        let exists = |x: &Path| x == Path::new("C:\\Game\\Config\\DefaultGame.ini");
+        assert_eq!(registry.resolve(&original, Access::Read, exists), None);
+        assert_eq!(registry.copy_on_write_target(&original, exists), Some(copy.clone()));
+
+        // Once copied, the copy is used and nothing is copied again.
+        let exists = |x: &Path| x == copy || x == Path::new("C:\\Game\\Config\\DefaultGame.ini");
+        assert_eq!(registry.resolve(&original, Access::Read, exists), Some(copy.clone()));
+        assert_eq!(registry.copy_on_write_target(&original, exists), None);
+    }
+
+    #[test]
+    fn test_copy_on_write_move_source() {
+        let mut registry = PathRegistry::new();
+        registry.register_copy_on_write("C:\\Game\\Config", ["D:\\Profile\\Config"], 0);
+
+        let source = NormalizedPath::new("C:\\Game\\Config\\DefaultGame.ini");
+        let copy = PathBuf::from("D:\\Profile\\Config\\DefaultGame.ini");
+
+        // A source only in the original directory can't be moved until it is copied.
+        let exists = |x: &Path| x == Path::new("C:\\Game\\Config\\DefaultGame.ini");
+        assert!(registry.is_original_only(&source, exists));
+        assert_eq!(registry.copy_on_write_target(&source, exists), Some(copy.clone()));
+
+        // Once copied, the copy is what gets moved.
+        let exists = |x: &Path| x == copy || x == Path::new("C:\\Game\\Config\\DefaultGame.ini");
+        assert!(!registry.is_original_only(&source, exists));
+        assert_eq!(registry.resolve(&source, Access::Read, exists), Some(copy.clone()));
+    }
+
+    #[test]
+    fn test_copy_on_write_skips_new_files_and_plain_overlays() {
+        let mut registry = PathRegistry::new();
+        registry.register_copy_on_write("C:\\Game\\Config", ["D:\\Profile\\Config"], 0);
+        registry.register("C:\\Game\\Mods", "D:\\MyMods");
+
+        let new_file = NormalizedPath::new("C:\\Game\\Config\\User.ini");
+        assert_eq!(registry.copy_on_write_target(&new_file, |_| false), None);
+
+        let mod_file = NormalizedPath::new("C:\\Game\\Mods\\mods.txt");
+        assert_eq!(registry.copy_on_write_target(&mod_file, |x| x == mod_file.original()), None);
+    }
+
+    #[test]
+    fn test_virtual_file() {
+        let mut registry = PathRegistry::new();
+        registry.register("C:\\Game\\Mods", "D:\\MyMods");
+        registry.hide("C:\\Game\\Mods\\mods.txt");
+        registry.register_virtual_file("C:\\Game\\Mods\\mods.txt", "T:\\Store\\0\\mods.txt");
+
+        let path = NormalizedPath::new("c:\\game\\mods\\MODS.TXT");
+        let backing = registry.resolve(&path, Access::Read, |_| false).expect("virtual file should resolve");
+        assert_eq!(backing, PathBuf::from("T:\\Store\\0\\mods.txt"));
+        assert!(!registry.is_hidden(&path));
+        assert_eq!(
+            registry.reverse_remap(&NormalizedPath::new(&backing)),
+            Some(PathBuf::from("C:\\Game\\Mods\\mods.txt"))
+        );
+
+        let layers = registry.lookup_layers(&NormalizedPath::new("C:\\Game\\Mods")).expect("mods has layers");
+        assert_eq!(layers.len(), 2);
+        assert_eq!(layers[0], PathBuf::from("T:\\Store\\0"));
+    }
+
+    #[test]
+    fn test_virtual_file_outside_mappings() {
+        let mut registry = PathRegistry::new();
+        registry.register_virtual_file(
+            "C:\\Game\\Binaries\\Win64\\UE4SS-settings.ini",
+            "T:\\Store\\0\\UE4SS-settings.ini",
+        );
+
+        assert!(registry.overlaps(&NormalizedPath::new("C:\\Game")));
+        assert!(!registry.is_empty());
+
+        let layers = registry.lookup_layers(&NormalizedPath::new("C:\\Game\\Binaries\\Win64"));
+        assert_eq!(layers.map(|x| x.len()), Some(2));
+        assert_eq!(registry.lookup_layers(&NormalizedPath::new("C:\\Game\\Binaries")), None);
+    }
+
+    #[test]
+    fn test_hidden_paths() {
+        let mut registry = PathRegistry::new();
+        registry.hide("C:\\Game\\Binaries\\Win64\\Mods\\mods.txt");
+        registry.hide("C:\\Game\\Content\\Paks\\pakchunk1*.pak");
+
+        assert!(registry.is_hidden(&NormalizedPath::new("c:\\game\\binaries\\win64\\mods\\MODS.TXT")));
+        assert!(registry.is_hidden(&NormalizedPath::new("C:\\Game\\Content\\Paks\\pakchunk10-Windows.pak")));
+        assert!(!registry.is_hidden(&NormalizedPath::new("C:\\Game\\Content\\Paks\\pakchunk0-Windows.pak")));
+        assert!(!registry.is_hidden(&NormalizedPath::new("C:\\Game\\Binaries\\Win64\\Mods")));
+    }
+
+    #[test]
+    fn test_hides_inside() {
+        let mut registry = PathRegistry::new();
+        registry.hide("C:\\Game\\Content\\Paks\\pakchunk1*.pak");
+
+        assert!(registry.hides_inside(&NormalizedPath::new("C:\\Game\\Content\\Paks")));
+        assert!(!registry.hides_inside(&NormalizedPath::new("C:\\Game")));
+        assert!(!registry.hides_inside(&NormalizedPath::new("C:\\Game\\Config")));
+
+        // Relative opens from further up can still reach hidden paths.
+        assert!(registry.overlaps(&NormalizedPath::new("C:\\Game")));
+    }
+
+    #[test]
+    fn test_reverse_remap() {
+        let mut registry = PathRegistry::new();
+        registry.register("C:\\Game\\Binaries\\Win64\\Mods", "D:\\MyMods");
+
+        let path = NormalizedPath::new("D:\\MyMods\\Keybinds\\Scripts\\main.lua");
+        assert_eq!(
+            registry.reverse_remap(&path),
+            Some(PathBuf::from("C:\\Game\\Binaries\\Win64\\Mods\\Keybinds\\Scripts\\main.lua"))
+        );
+
+        let path = NormalizedPath::new("d:\\mymods");
+        assert_eq!(registry.reverse_remap(&path), Some(PathBuf::from("C:\\Game\\Binaries\\Win64\\Mods")));
+    }
+
+    #[test]
+    fn test_reverse_remap_outside_layers() {
+        let mut registry = PathRegistry::new();
+        registry.register("C:\\Game\\Mods", "D:\\MyMods");
+
+        assert_eq!(registry.reverse_remap(&NormalizedPath::new("D:\\Other\\x.lua")), None);
+        assert_eq!(registry.reverse_remap(&NormalizedPath::new("D:\\MyModsBackup\\x.lua")), None);
+        assert_eq!(registry.reverse_remap(&NormalizedPath::new("C:\\Game\\Mods\\x.lua")), None);
+    }
+
+    #[test]
+    fn test_reverse_remap_overlay_layers() {
+        let mut registry = PathRegistry::new();
+        registry.register_overlay("C:\\Game\\Mods", ["D:\\A", "E:\\B"], 0);
+
+        let path = NormalizedPath::new("E:\\B\\shared\\Types.lua");
+        assert_eq!(registry.reverse_remap(&path), Some(PathBuf::from("C:\\Game\\Mods\\shared\\Types.lua")));
+    }
+
+    #[test]
+    fn test_reverse_remap_nested_layers() {
+        let mut registry = PathRegistry::new();
+        registry.register("C:\\Game\\Mods", "D:\\Mods");
+        registry.register("C:\\Game\\Content\\Paks\\LogicMods", "D:\\Mods\\Paks");
+
+        let path = NormalizedPath::new("D:\\Mods\\Paks\\a.pak");
+        assert_eq!(registry.reverse_remap(&path), Some(PathBuf::from("C:\\Game\\Content\\Paks\\LogicMods\\a.pak")));
+    }
+
+    #[test]
+    fn test_reverse_remap_ignores_replaced_mapping() {
+        let mut registry = PathRegistry::new();
+        registry.register("C:\\Game\\Mods", "D:\\Old");
+        registry.register("C:\\Game\\Mods", "D:\\New");
+
+        assert_eq!(registry.reverse_remap(&NormalizedPath::new("D:\\Old\\x.lua")), None);
+        assert_eq!(registry.reverse_remap(&NormalizedPath::new("D:\\New\\x.lua")), Some(PathBuf::from("C:\\Game\\Mods\\x.lua")));
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let mut registry = PathRegistry::new();
+        registry.register("C:\\Game\\Content\\Paks\\LogicMods", "D:\\MyPaks");
+
+        assert!(registry.overlaps(&NormalizedPath::new("C:\\Game\\Content")));
+        assert!(registry.overlaps(&NormalizedPath::new("C:\\Game\\Content\\Paks\\LogicMods\\Sub")));
+        assert!(!registry.overlaps(&NormalizedPath::new("C:\\Game\\Config")));
+    }
+
+    #[test]
+    fn test_overlay_without_layers_is_ignored() {
+        let mut registry = PathRegistry::new();
+        registry.register_overlay("C:\\Game\\Mods", Vec::<&str>::new(), 0);
+
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn test_mapping_without_layers_resolves_nothing() {
+        let mut registry = PathRegistry::new();
+        registry.insert(PathMapping::overlay("C:\\Game\\Mods", Vec::new(), 0));
+
+        let path = NormalizedPath::new("C:\\Game\\Mods\\test.lua");
+        assert_eq!(registry.resolve(&path, Access::Write, |_| false), None);
     }
 }
@@ -1,11 +1,185 @@
-use std::path::PathBuf;
+use std::cell::Cell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
 
+use log::{debug, error};
+
+use super::cwd;
 use super::normalized::NormalizedPath;
-use super::registry::PATH_REGISTRY;
+use super::registry::{Access, PATH_REGISTRY};
+
+thread_local! {
+    static BYPASS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Held while copying a file for a copy-on-write mapping, so two threads opening the same
+/// file for writing don't both copy it.
+static COPY_LOCK: Mutex<()> = Mutex::new(());
+
+/// Resets the bypass flag when dropped, even if the bypassed closure panics.
+struct BypassGuard(bool);
+
+impl Drop for BypassGuard {
+    fn drop(&mut self) {
+        BYPASS.set(self.0);
+    }
+}
 
-/// Re-map a path through the global path registry.
+/// Run `f` with remapping disabled on the current thread.
+///
+/// Registry lookups check whether files exist, and the shim enumerates real directories
+/// on behalf of the game. Those calls go through the hooked file APIs themselves and must
+/// see the real file system.
+pub fn bypass_remapping<T>(f: impl FnOnce() -> T) -> T {
+    let _guard = BypassGuard(BYPASS.replace(true));
+    f()
+}
+
+/// Re-map a path that is about to be read through the global path registry.
+///
+/// Relative paths are resolved against the virtual current directory while one is set. They
+/// always come back absolute then, since the real current directory is somewhere else.
 pub fn remap_path(path: &NormalizedPath) -> Option<PathBuf> {
-    PATH_REGISTRY.get().and_then(|registry| registry.try_remap(path))
+    remap(path, Access::Read)
+}
+
+/// Re-map a path that is about to be created, written, renamed or removed through the global
+/// path registry. Paths inside a mapping always end up in one of its layers.
+pub fn remap_path_for_write(path: &NormalizedPath) -> Option<PathBuf> {
+    remap(path, Access::Write)
+}
+
+fn remap(path: &NormalizedPath, access: Access) -> Option<PathBuf> {
+    if BYPASS.get() {
+        return None;
+    }
+
+    let registry = PATH_REGISTRY.get()?;
+    match cwd::absolute(path) {
+        Some(absolute) => {
+            let remapped = bypass_remapping(|| registry.try_remap(&absolute, access));
+            Some(remapped.unwrap_or_else(|| absolute.to_path_buf()))
+        }
+        None => bypass_remapping(|| registry.try_remap(path, access)),
+    }
+}
+
+/// Copy the file at `path` into the writable layer of its mapping if it is inside a
+/// copy-on-write mapping and hasn't been copied yet.
+///
+/// Called before a file is opened for writing, so the open that follows is remapped to the
+/// copy. Returns whether a copy was made. Failures are logged and leave the original in place.
+pub fn copy_on_write(path: &NormalizedPath) -> bool {
+    if BYPASS.get() {
+        return false;
+    }
+    let Some(registry) = PATH_REGISTRY.get() else {
+        return false;
+    };
+
+    let absolute = cwd::absolute(path);
+    let path = absolute.as_ref().unwrap_or(path);
+
+    bypass_remapping(|| {
+        // Most writes don't need a copy, so only take the lock once one looks necessary and check
+        // again under it in case another thread got there first.
+        if registry.copy_on_write_target(path, Path::exists).is_none() {
+            return false;
+        }
+
+        let _guard = COPY_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(target) = registry.copy_on_write_target(path, Path::exists) else {
+            return false;
+        };
+        if !path.original().is_file() {
+            return false;
+        }
+
+        let copied = target
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::copy(path.original(), &target));
+
+        match copied {
+            Ok(_) => {
+                debug!("[copy_on_write] Copied {} to {}", path.original().display(), target.display());
+                true
+            }
+            Err(e) => {
+                error!(
+                    "[copy_on_write] Failed to copy {} to {}: {e}",
+                    path.original().display(),
+                    target.display()
+                );
+                false
+            }
+        }
+    })
+}
+
+/// Look up every real location backing `path` through the global path registry.
+pub fn lookup_layers(path: &NormalizedPath) -> Option<Vec<PathBuf>> {
+    if BYPASS.get() {
+        return None;
+    }
+
+    let absolute = cwd::absolute(path);
+    PATH_REGISTRY.get()?.lookup_layers(absolute.as_ref().unwrap_or(path))
+}
+
+/// Map a real path back to its virtual path through the global path registry.
+pub fn reverse_remap_path(path: &NormalizedPath) -> Option<PathBuf> {
+    if BYPASS.get() {
+        return None;
+    }
+
+    PATH_REGISTRY.get()?.reverse_remap(path)
+}
+
+/// Whether `path` is hidden through the global path registry.
+pub fn is_hidden(path: &NormalizedPath) -> bool {
+    if BYPASS.get() {
+        return false;
+    }
+
+    let absolute = cwd::absolute(path);
+    PATH_REGISTRY.get().is_some_and(|x| x.is_hidden(absolute.as_ref().unwrap_or(path)))
+}
+
+/// Whether `path` only exists in the original directory of its mapping, through the global
+/// path registry.
+pub fn is_original_only(path: &NormalizedPath) -> bool {
+    if BYPASS.get() {
+        return false;
+    }
+
+    let Some(registry) = PATH_REGISTRY.get() else {
+        return false;
+    };
+
+    let absolute = cwd::absolute(path);
+    bypass_remapping(|| registry.is_original_only(absolute.as_ref().unwrap_or(path), Path::exists))
+}
+
+/// Whether listing `path` may have to leave hidden entries out, through the global path registry.
+pub fn hides_inside(path: &NormalizedPath) -> bool {
+    if BYPASS.get() {
+        return false;
+    }
+
+    let absolute = cwd::absolute(path);
+    PATH_REGISTRY.get().is_some_and(|x| x.hides_inside(absolute.as_ref().unwrap_or(path)))
+}
+
+/// Whether `path` is inside a mapping or contains one, through the global path registry.
+pub fn overlaps_mapping(path: &NormalizedPath) -> bool {
+    if BYPASS.get() {
+        return false;
+    }
+
+    let absolute = cwd::absolute(path);
+    PATH_REGISTRY.get().is_some_and(|x| x.overlaps(absolute.as_ref().unwrap_or(path)))
 }
 
 /// Splice a path from one root onto another.
@@ -92,6 +266,16 @@ mod tests {
         assert_eq!(result, Some(PathBuf::from("D:\\MyMods\\test.lua")));
     }
 
+    #[test]
+    fn test_splice_path_preserves_remainder_case() {
+        let path = NormalizedPath::new("C:\\game\\mods\\Other\\Test.lua");
+        let source = NormalizedPath::new("C:\\Game\\Mods");
+        let target = NormalizedPath::new("D:\\MyMods");
+
+        let result = splice_path(&path, &source, &target);
+        assert_eq!(result, Some(PathBuf::from("D:\\MyMods\\Other\\Test.lua")));
+    }
+
     // Edge case: UNC paths should NOT match local paths
     #[test]
     fn test_unc_path_not_remapped() {
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+
+use super::normalized::NormalizedPath;
+
+struct Node<T> {
+    value: Option<T>,
+    children: HashMap<OsString, Node<T>>,
+}
+
+impl<T> Node<T> {
+    fn new() -> Self {
+        Node {
+            value: None,
+            children: HashMap::new(),
+        }
+    }
+}
+
+/// Prefix tree keyed on the normalized components of a path.
+/// Lookups cost time proportional to the depth of the path, not the number of entries.
+pub struct PathTrie<T> {
+    root: Node<T>,
+    len: usize,
+}
+
+impl<T> PathTrie<T> {
+    pub fn new() -> Self {
+        PathTrie {
+            root: Node::new(),
+            len: 0,
+        }
+    }
+
+    /// Insert a value at `key`, returning the value previously stored there, if any.
+    pub fn insert(&mut self, key: &NormalizedPath, value: T) -> Option<T> {
+        let mut node = &mut self.root;
+        for comp in key.components() {
+            node = node
+                .children
+                .entry(comp.as_os_str().to_os_string())
+                .or_insert_with(Node::new);
+        }
+
+        let previous = node.value.replace(value);
+        if previous.is_none() {
+            self.len += 1;
+        }
+
+        previous
+    }
+
+    /// Find the value stored at the longest prefix of `path`.
+    pub fn longest_prefix(&self, path: &NormalizedPath) -> Option<&T> {
+        let mut node = &self.root;
+        let mut best = node.value.as_ref();
+
+        for comp in path.components() {
+            match node.children.get(comp.as_os_str()) {
+                Some(child) => node = child,
+                None => break,
+            }
+
+            if let Some(value) = node.value.as_ref() {
+                best = Some(value);
+            }
+        }
+
+        best
+    }
+
+    /// Whether any value is stored at `path` or below it.
+    pub fn has_descendants(&self, path: &NormalizedPath) -> bool {
+        let mut node = &self.root;
+        for comp in path.components() {
+            match node.children.get(comp.as_os_str()) {
+                Some(child) => node = child,
+                None => return false,
+            }
+        }
+
+        // Nodes are only created on the way to a value, so reaching one means a value lies below.
+        true
+    }
+
+    /// Call `f` on every stored value, in no particular order.
+    pub fn for_each_mut(&mut self, mut f: impl FnMut(&mut T)) {
+        let mut stack = vec![&mut self.root];
+        while let Some(node) = stack.pop() {
+            if let Some(value) = node.value.as_mut() {
+                f(value);
+            }
+            stack.extend(node.children.values_mut());
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trie_exact_and_nested_lookup() {
+        let mut trie = PathTrie::new();
+        trie.insert(&NormalizedPath::new("C:\\Game\\Mods"), 1);
+
+        assert_eq!(trie.longest_prefix(&NormalizedPath::new("C:\\Game\\Mods")), Some(&1));
+        assert_eq!(trie.longest_prefix(&NormalizedPath::new("C:\\Game\\Mods\\a\\b.lua")), Some(&1));
+        assert_eq!(trie.longest_prefix(&NormalizedPath::new("C:\\Game")), None);
+    }
+
+    #[test]
+    fn test_trie_longest_prefix_wins() {
+        let mut trie = PathTrie::new();
+        trie.insert(&NormalizedPath::new("C:\\Game\\Mods"), "mods");
+        trie.insert(&NormalizedPath::new("C:\\Game\\Mods\\Special"), "special");
+
+        let path = NormalizedPath::new("C:\\Game\\Mods\\Special\\test.lua");
+        assert_eq!(trie.longest_prefix(&path), Some(&"special"));
+
+        let path = NormalizedPath::new("C:\\Game\\Mods\\Other\\test.lua");
+        assert_eq!(trie.longest_prefix(&path), Some(&"mods"));
+    }
+
+    #[test]
+    fn test_trie_is_case_insensitive() {
+        let mut trie = PathTrie::new();
+        trie.insert(&NormalizedPath::new("C:\\Game\\Mods"), 1);
+
+        assert_eq!(trie.longest_prefix(&NormalizedPath::new("c:\\GAME\\mods\\x")), Some(&1));
+    }
+
+    #[test]
+    fn test_trie_similar_prefix_not_matched() {
+        let mut trie = PathTrie::new();
+        trie.insert(&NormalizedPath::new("C:\\Game\\Mods"), 1);
+
+        assert_eq!(trie.longest_prefix(&NormalizedPath::new("C:\\Game\\ModsBackup\\x")), None);
+    }
+
+    #[test]
+    fn test_trie_has_descendants() {
+        let mut trie = PathTrie::new();
+        trie.insert(&NormalizedPath::new("C:\\Game\\Content\\Paks\\LogicMods"), 1);
+
+        assert!(trie.has_descendants(&NormalizedPath::new("C:\\Game\\Content")));
+        assert!(trie.has_descendants(&NormalizedPath::new("c:\\game\\content\\paks\\logicmods")));
+        assert!(!trie.has_descendants(&NormalizedPath::new("C:\\Game\\Config")));
+        assert!(!trie.has_descendants(&NormalizedPath::new("C:\\Game\\Content\\Paks\\LogicMods\\a.pak")));
+    }
+
+    #[test]
+    fn test_trie_insert_replaces() {
+        let mut trie = PathTrie::new();
+        assert_eq!(trie.insert(&NormalizedPath::new("C:\\Game\\Mods"), 1), None);
+        assert_eq!(trie.insert(&NormalizedPath::new("c:\\game\\mods"), 2), Some(1));
+
+        assert_eq!(trie.len(), 1);
+        assert_eq!(trie.longest_prefix(&NormalizedPath::new("C:\\Game\\Mods")), Some(&2));
+    }
+
+    #[test]
+    fn test_trie_for_each_mut() {
+        let mut trie = PathTrie::new();
+        trie.insert(&NormalizedPath::new("C:\\Game\\Mods"), 1);
+        trie.insert(&NormalizedPath::new("C:\\Game\\Mods\\Special"), 2);
+        trie.for_each_mut(|x| *x *= 10);
+
+        assert_eq!(trie.longest_prefix(&NormalizedPath::new("C:\\Game\\Mods\\x")), Some(&10));
+        assert_eq!(trie.longest_prefix(&NormalizedPath::new("C:\\Game\\Mods\\Special\\x")), Some(&20));
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::{env, fs, io, process, ptr};
+
+use windows_sys::Win32::Foundation::{CloseHandle, STILL_ACTIVE};
+use windows_sys::Win32::System::Threading::{GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION};
+
+use super::normalized::NormalizedPath;
+
+/// Where the backing files of in-memory virtual files are written.
+///
+/// Files that appear in the same virtual directory share a backing directory, so that directory
+/// can be listed as one more layer of the virtual one.
+pub struct VirtualFileStore {
+    root: PathBuf,
+    dirs: HashMap<NormalizedPath, PathBuf>,
+}
+
+impl VirtualFileStore {
+    /// A store writing into `root`. Whatever an earlier launch left there is removed.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        let _ = fs::remove_dir_all(&root);
+
+        VirtualFileStore {
+            root,
+            dirs: HashMap::new(),
+        }
+    }
+
+    /// The store for the current process of the game run by `exe`, under the temp directory.
+    ///
+    /// Every running instance of a game gets its own directory, so a second instance can't pull
+    /// the backing files out from under the first. Directories left behind by instances that
+    /// have exited are removed.
+    pub fn for_game(exe: &Path) -> Self {
+        let name = exe.file_stem().map_or_else(|| "game".into(), |x| x.to_string_lossy());
+        let game_dir = env::temp_dir().join("unreal-shimloader").join(&*name);
+
+        remove_stale_stores(&game_dir, is_running);
+        VirtualFileStore::new(game_dir.join(process::id().to_string()))
+    }
+
+    /// Write `contents` to the backing file of the virtual file at `path` and return where it is.
+    pub fn write(&mut self, path: &NormalizedPath, contents: impl AsRef<[u8]>) -> io::Result<PathBuf> {
+        let (Some(parent), Some(file_name)) = (path.original().parent(), path.original().file_name()) else {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{path:?} is not a file path")));
+        };
+
+        let index = self.dirs.len();
+        let dir = self
+            .dirs
+            .entry(NormalizedPath::new(parent))
+            .or_insert_with(|| self.root.join(index.to_string()));
+        fs::create_dir_all(&*dir)?;
+
+        let backing = dir.join(file_name);
+        fs::write(&backing, contents)?;
+        Ok(backing)
+    }
+}
+
+/// Remove the store directories in `game_dir` whose process is no longer running.
+///
+/// Anything that isn't named after a process id is left alone.
+fn remove_stale_stores(game_dir: &Path, is_running: impl Fn(u32) -> bool) {
+    let Ok(entries) = fs::read_dir(game_dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|x| x.parse::<u32>().ok()) else {
+            continue;
+        };
+        if pid != process::id() && !is_running(pid) {
+            let _ = fs::remove_dir_all(entry.path());
+        }
+    }
+}
+
+fn is_running(pid: u32) -> bool {
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle == 0 {
+            return false;
+        }
+
+        let mut exit_code = 0;
+        let running = GetExitCodeProcess(handle, ptr::addr_of_mut!(exit_code)) != 0 && exit_code == STILL_ACTIVE as u32;
+        CloseHandle(handle);
+        running
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::Fixture;
+
+    #[test]
+    fn test_write_groups_files_by_directory() {
+        let fixture = Fixture::new("virtual-files-groups");
+        let mut store = VirtualFileStore::new(fixture.path("store"));
+        let mods_txt = store.write(&NormalizedPath::new("C:\\Game\\Mods\\mods.txt"), "Keybinds : 1\r\n");
+        let other = store.write(&NormalizedPath::new("C:\\Game\\Mods\\other.txt"), "");
+        let settings = store.write(&NormalizedPath::new("C:\\Game\\UE4SS-settings.ini"), "[General]\r\n");
+
+        let mods_txt = mods_txt.expect("backing file should be written");
+        assert_eq!(fs::read_to_string(&mods_txt).ok().as_deref(), Some("Keybinds : 1\r\n"));
+        assert_eq!(other.expect("backing file should be written").parent(), mods_txt.parent());
+        assert_ne!(settings.expect("backing file should be written").parent(), mods_txt.parent());
+    }
+
+    #[test]
+    fn test_new_store_starts_empty() {
+        let fixture = Fixture::new("virtual-files-restart");
+        let mut store = VirtualFileStore::new(fixture.path("store"));
+        let backing = store
+            .write(&NormalizedPath::new("C:\\Game\\Mods\\mods.txt"), "")
+            .expect("backing file should be written");
+
+        VirtualFileStore::new(fixture.path("store"));
+        assert!(!backing.exists());
+    }
+
+    #[test]
+    fn test_remove_stale_stores_keeps_running_processes() {
+        let fixture = Fixture::new("virtual-files-stale");
+        let running = fixture.file("game/100/Mods/mods.txt");
+        let exited = fixture.file("game/200/Mods/mods.txt");
+        let current = fixture.file(&format!("game/{}/Mods/mods.txt", process::id()));
+        let other = fixture.file("game/notes/readme.txt");
+
+        remove_stale_stores(&fixture.path("game"), |pid| pid == 100);
+
+        assert!(running.exists());
+        assert!(!fixture.path("game/200").exists());
+        assert!(!exited.exists());
+        assert!(current.exists());
+        assert!(other.exists());
+    }
+
+    #[test]
+    fn test_write_rejects_paths_without_a_file_name() {
+        let fixture = Fixture::new("virtual-files-invalid");
+        let mut store = VirtualFileStore::new(fixture.path("store"));
+        assert!(store.write(&NormalizedPath::new("C:\\"), "").is_err());
+    }
+}
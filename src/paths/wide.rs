@@ -28,7 +28,7 @@ mod tests {
     fn test_path_to_widestring_roundtrip() {
         let original = Path::new("C:\\Game\\Mods\\test.lua");
         let wide = path_to_widestring(original);
-        let back = wide.to_string().unwrap();
+        let back = wide.to_string().expect("path should round-trip");
         assert_eq!(back, "C:\\Game\\Mods\\test.lua");
     }
 }
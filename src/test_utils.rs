@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+use std::{env, fs, process};
+
+/// A directory tree under the system temp directory, removed on drop.
+pub struct Fixture {
+    root: PathBuf,
+}
+
+impl Fixture {
+    pub fn new(name: &str) -> Self {
+        let root = env::temp_dir().join(format!("shimloader-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).expect("failed to create fixture");
+        Fixture { root }
+    }
+
+    /// Resolve a `/` separated path inside the fixture.
+    pub fn path(&self, relative: &str) -> PathBuf {
+        relative.split('/').fold(self.root.clone(), |acc, x| acc.join(x))
+    }
+
+    pub fn dir(&self, relative: &str) -> PathBuf {
+        let path = self.path(relative);
+        fs::create_dir_all(&path).expect("failed to create fixture directory");
+        path
+    }
+
+    pub fn file(&self, relative: &str) -> PathBuf {
+        let path = self.path(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).expect("failed to create fixture directory");
+        }
+        fs::write(&path, b"").expect("failed to create fixture file");
+        path
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
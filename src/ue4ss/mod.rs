@@ -0,0 +1,5 @@
+mod mod_list;
+mod settings;
+
+pub use mod_list::{find_mods, merge_mod_list};
+pub use settings::{merge_ini, SettingOverride};
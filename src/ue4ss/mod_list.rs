@@ -0,0 +1,142 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The entry UE4SS ships last in `mods.txt`. Its comment asks for it to stay at the bottom.
+const KEYBINDS_MOD: &str = "Keybinds";
+
+/// Whether `dir` holds a UE4SS mod: a Lua mod with `Scripts\main.lua`, a C++ mod with
+/// `dlls\main.dll`, or any mod switched on with an `enabled.txt` marker.
+fn is_mod_dir(dir: &Path) -> bool {
+    dir.join("Scripts").join("main.lua").is_file()
+        || dir.join("dlls").join("main.dll").is_file()
+        || dir.join("enabled.txt").is_file()
+}
+
+/// Find the mods in every mod directory, in the order the directories are layered.
+///
+/// Mods are sorted by name within each directory. A mod found in more than one directory
+/// is listed once, the way the overlay only shows the first one.
+pub fn find_mods(mod_dirs: &[PathBuf]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut mods = Vec::new();
+
+    for dir in mod_dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+
+        let mut names = entries
+            .filter_map(Result::ok)
+            .filter(|x| is_mod_dir(&x.path()))
+            .filter_map(|x| x.file_name().into_string().ok())
+            .collect::<Vec<_>>();
+        names.sort_by_key(|x| x.to_lowercase());
+
+        mods.extend(names.into_iter().filter(|x| seen.insert(x.to_lowercase())));
+    }
+
+    mods
+}
+
+/// The mod name of a `mods.txt` line, or None for comments and blank lines.
+fn entry_name(line: &str) -> Option<&str> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with(';') {
+        return None;
+    }
+
+    let name = line.split_once(':').map_or(line, |(name, _)| name).trim();
+    Some(name).filter(|x| !x.is_empty())
+}
+
+/// Merge the mods found on disk into the user's own `mods.txt`.
+///
+/// Everything in `overrides` is kept as it is, so a mod the user switched off stays off.
+/// Mods it doesn't mention are added enabled, in front of the `Keybinds` entry and the
+/// comments above it if there is one, otherwise at the end.
+pub fn merge_mod_list(overrides: &str, mods: &[String]) -> String {
+    let mut lines = overrides
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+
+    let listed = lines
+        .iter()
+        .filter_map(|x| entry_name(x))
+        .map(str::to_lowercase)
+        .collect::<HashSet<_>>();
+    let added = mods
+        .iter()
+        .filter(|x| !listed.contains(&x.to_lowercase()))
+        .map(|x| format!("{x} : 1"));
+
+    let mut insert_at = lines
+        .iter()
+        .position(|x| entry_name(x).is_some_and(|x| x.eq_ignore_ascii_case(KEYBINDS_MOD)))
+        .unwrap_or(lines.len());
+    while insert_at > 0 && lines[insert_at - 1].trim_start().starts_with(';') {
+        insert_at -= 1;
+    }
+    lines.splice(insert_at..insert_at, added);
+
+    let mut merged = lines.join("\r\n");
+    merged.push_str("\r\n");
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::Fixture;
+
+    fn names(mods: &[&str]) -> Vec<String> {
+        mods.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_find_mods() {
+        let fixture = Fixture::new("mod-list");
+        fixture.file("a/Zoom/Scripts/main.lua");
+        fixture.file("a/NativeMod/dlls/main.dll");
+        fixture.file("a/Marked/enabled.txt");
+        fixture.file("a/shared/Types.lua");
+        fixture.file("b/zoom/Scripts/main.lua");
+        fixture.file("b/Another/Scripts/main.lua");
+
+        let mods = find_mods(&[fixture.path("a"), fixture.path("b"), fixture.path("missing")]);
+        assert_eq!(mods, names(&["Marked", "NativeMod", "Zoom", "Another"]));
+    }
+
+    #[test]
+    fn test_merge_into_empty_list() {
+        assert_eq!(merge_mod_list("", &names(&["Zoom", "Another"])), "Zoom : 1\r\nAnother : 1\r\n");
+    }
+
+    #[test]
+    fn test_merge_keeps_overrides() {
+        let overrides = "CheatManagerEnablerMod : 1\nZoom : 0\n";
+        assert_eq!(
+            merge_mod_list(overrides, &names(&["zoom", "Another"])),
+            "CheatManagerEnablerMod : 1\r\nZoom : 0\r\nAnother : 1\r\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_keeps_keybinds_last() {
+        let overrides = "\u{feff}LineTraceMod : 0\r\n\r\n; Built-in keybinds, do not move up!\r\nKeybinds : 1\r\n";
+        assert_eq!(
+            merge_mod_list(overrides, &names(&["Zoom"])),
+            "LineTraceMod : 0\r\n\r\nZoom : 1\r\n; Built-in keybinds, do not move up!\r\nKeybinds : 1\r\n"
+        );
+    }
+
+    #[test]
+    fn test_entry_name() {
+        assert_eq!(entry_name("  Zoom : 1"), Some("Zoom"));
+        assert_eq!(entry_name("Zoom"), Some("Zoom"));
+        assert_eq!(entry_name("; Zoom : 1"), None);
+        assert_eq!(entry_name("   "), None);
+    }
+}
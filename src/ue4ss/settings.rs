@@ -0,0 +1,225 @@
+/// A single `Section.Key=Value` override for `UE4SS-settings.ini`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettingOverride {
+    pub section: String,
+    pub key: String,
+    pub value: String,
+}
+
+impl SettingOverride {
+    /// Parse an override of the form `Section.Key=Value`. The value may be empty.
+    pub fn parse(spec: &str) -> Option<SettingOverride> {
+        let (name, value) = spec.split_once('=')?;
+        let (section, key) = name.split_once('.')?;
+        let (section, key) = (section.trim(), key.trim());
+        if section.is_empty() || key.is_empty() {
+            return None;
+        }
+
+        Some(SettingOverride {
+            section: section.to_string(),
+            key: key.to_string(),
+            value: value.trim().to_string(),
+        })
+    }
+}
+
+/// What a single line of an INI file holds.
+#[derive(Debug, PartialEq, Eq)]
+enum Line<'a> {
+    Section(&'a str),
+    /// A key and the text of the line up to the start of its value, which is kept when the
+    /// value is replaced.
+    Entry { key: &'a str, head: &'a str },
+    /// Comments, blank lines and anything else, which are kept as they are.
+    Other,
+}
+
+fn parse_line(line: &str) -> Line<'_> {
+    let trimmed = line.trim();
+    if trimmed.starts_with(';') || trimmed.starts_with('#') {
+        return Line::Other;
+    }
+
+    if let Some(section) = trimmed.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+        return Line::Section(section.trim());
+    }
+
+    let Some(equals) = line.find('=') else {
+        return Line::Other;
+    };
+    let key = line[..equals].trim();
+    if key.is_empty() {
+        return Line::Other;
+    }
+
+    let value_start = equals + 1 + (line[equals + 1..].len() - line[equals + 1..].trim_start().len());
+    Line::Entry { key, head: &line[..value_start] }
+}
+
+/// Apply `overrides` to the text of an INI file.
+///
+/// Comments, blank lines, ordering and the spacing around `=` are kept. Existing keys have
+/// their value replaced, missing keys are added after the last entry of their section and
+/// missing sections are added at the end. Sections and keys are matched ignoring ASCII case.
+pub fn merge_ini(text: &str, overrides: &[SettingOverride]) -> String {
+    let (bom, text) = match text.strip_prefix('\u{feff}') {
+        Some(rest) => ("\u{feff}", rest),
+        None => ("", text),
+    };
+    let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut lines = text.lines().map(str::to_string).collect::<Vec<_>>();
+
+    for setting in overrides {
+        let mut section_end = None;
+        let mut current = None;
+        let mut replaced = false;
+
+        for (i, line) in lines.iter_mut().enumerate() {
+            let replacement = match parse_line(line) {
+                Line::Section(name) => {
+                    current = Some(name.eq_ignore_ascii_case(&setting.section));
+                    if current == Some(true) && section_end.is_none() {
+                        section_end = Some(i + 1);
+                    }
+                    None
+                }
+                Line::Entry { key, head } if current == Some(true) => {
+                    section_end = Some(i + 1);
+                    // An empty value has no spacing of its own after `=`, so mirror the one before it.
+                    let spaced = head.strip_suffix('=').is_some_and(|x| x.ends_with(char::is_whitespace));
+                    let separator = if spaced { " " } else { "" };
+
+                    key.eq_ignore_ascii_case(&setting.key).then(|| format!("{head}{separator}{}", setting.value))
+                }
+                Line::Entry { .. } | Line::Other => None,
+            };
+
+            if let Some(replacement) = replacement {
+                *line = replacement;
+                replaced = true;
+            }
+        }
+
+        if replaced {
+            continue;
+        }
+
+        let entry = format!("{} = {}", setting.key, setting.value);
+        if let Some(end) = section_end {
+            lines.insert(end, entry);
+        } else {
+            if lines.last().is_some_and(|x| !x.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push(format!("[{}]", setting.section));
+            lines.push(entry);
+        }
+    }
+
+    let mut merged = lines.join(newline);
+    merged.push_str(newline);
+    format!("{bom}{merged}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setting(spec: &str) -> SettingOverride {
+        SettingOverride::parse(spec).expect("override should parse")
+    }
+
+    const SETTINGS: &str = "\
+[Overrides]
+; Path to the 'Mods' folder
+ModsFolderPath =
+
+[General]
+EnableHotReloadSystem = 0
+; Whether the cache system for AOBs will be used.
+UseCache = 1
+
+; Hot reload key
+[Debug]
+ConsoleEnabled = 0
+";
+
+    #[test]
+    fn test_parse_override() {
+        assert_eq!(
+            SettingOverride::parse("General.EnableHotReloadSystem=1"),
+            Some(SettingOverride {
+                section: "General".to_string(),
+                key: "EnableHotReloadSystem".to_string(),
+                value: "1".to_string(),
+            })
+        );
+        assert_eq!(setting("Overrides.ModsFolderPath=").value, "");
+        assert_eq!(setting("Debug.Path=C:\\a=b").value, "C:\\a=b");
+    }
+
+    #[test]
+    fn test_parse_override_rejects_malformed() {
+        assert_eq!(SettingOverride::parse("EnableHotReloadSystem=1"), None);
+        assert_eq!(SettingOverride::parse("General.EnableHotReloadSystem"), None);
+        assert_eq!(SettingOverride::parse(".Key=1"), None);
+        assert_eq!(SettingOverride::parse("General.=1"), None);
+    }
+
+    #[test]
+    fn test_replace_existing_value() {
+        let merged = merge_ini(SETTINGS, &[setting("general.enablehotreloadsystem=1")]);
+        assert_eq!(merged, SETTINGS.replace("EnableHotReloadSystem = 0", "EnableHotReloadSystem = 1"));
+    }
+
+    #[test]
+    fn test_replace_empty_value() {
+        let merged = merge_ini(SETTINGS, &[setting("Overrides.ModsFolderPath=D:\\Mods")]);
+        assert_eq!(merged, SETTINGS.replace("ModsFolderPath =", "ModsFolderPath = D:\\Mods"));
+    }
+
+    #[test]
+    fn test_same_key_in_other_section_is_left_alone() {
+        let text = "[A]\nKey=1\n[B]\nKey=1\n";
+        assert_eq!(merge_ini(text, &[setting("B.Key=2")]), "[A]\nKey=1\n[B]\nKey=2\n");
+    }
+
+    #[test]
+    fn test_add_key_to_existing_section() {
+        let merged = merge_ini(SETTINGS, &[setting("General.MaxMemoryUsageDuringAsmScanning=0")]);
+        assert_eq!(
+            merged,
+            SETTINGS.replace("UseCache = 1\n", "UseCache = 1\nMaxMemoryUsageDuringAsmScanning = 0\n")
+        );
+    }
+
+    #[test]
+    fn test_add_key_to_empty_section() {
+        let merged = merge_ini("[General]\n\n[Debug]\n", &[setting("General.UseCache=0")]);
+        assert_eq!(merged, "[General]\nUseCache = 0\n\n[Debug]\n");
+    }
+
+    #[test]
+    fn test_add_missing_section() {
+        let merged = merge_ini("[General]\nUseCache = 1\n", &[setting("Threads.SigScannerNumThreads=4")]);
+        assert_eq!(merged, "[General]\nUseCache = 1\n\n[Threads]\nSigScannerNumThreads = 4\n");
+    }
+
+    #[test]
+    fn test_empty_file() {
+        let merged = merge_ini("", &[setting("General.EnableHotReloadSystem=1")]);
+        assert_eq!(merged, "[General]\nEnableHotReloadSystem = 1\n");
+    }
+
+    #[test]
+    fn test_keeps_crlf_and_bom() {
+        let merged = merge_ini("\u{feff}[General]\r\nUseCache=1\r\n", &[setting("General.UseCache=0")]);
+        assert_eq!(merged, "\u{feff}[General]\r\nUseCache=0\r\n");
+    }
+
+    #[test]
+    fn test_no_overrides_keeps_text() {
+        assert_eq!(merge_ini(SETTINGS, &[]), SETTINGS);
+    }
+}